@@ -0,0 +1,218 @@
+use chrono::{NaiveDate, NaiveTime};
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+use crate::{Change, Priority, Task, dates, history::{History, Step}, recurrence::{self, Series}, storage, subjects::{self, Subject}, text};
+
+pub const USAGE: &str = "\
+usage: doras-todo [--file <path>] [command]
+
+with no command, opens the full screen app
+
+commands:
+  add <subject> <date> [time] <description...> [--repeat <rule>] [--priority <level>]
+                                          add a task, optionally due at a time or repeating
+  list [--all]                            list tasks, hiding finished ones unless --all
+  due [date]                              list unfinished tasks due by a date (default today)
+  done <number>                           mark a task as finished
+  rm <number>                             delete a task, or skip one occurrence of a repeating one
+  stop <number>                           stop the series a repeating task belongs to
+  help                                    show this message
+
+task numbers are the ones shown by list and due. dates can be written like
+2025-09-04, 9/4, tomorrow, fri, next monday or in 3 days. repeat rules look like
+\"daily\", \"weekly on mon,wed\", \"every 3 days\" or \"monthly on 15\", and can end with
+\"until 12/19\" or \"10 times\". priorities are low, normal, high and urgent";
+
+fn usage_error(message: &str) -> Error {
+    return Error::new(ErrorKind::InvalidInput, format!("{message}\n\n{USAGE}"));
+}
+
+fn subject_name(subjects: &[Subject], id: u32) -> String {
+    return subjects.iter().find(|subject| subject.id == id).map(|subject| subject.name.clone()).unwrap_or(format!("#{id}"));
+}
+
+// one line per task, numbered by position in the task file so done and rm can refer back to it.
+// tasks from `numbered` on are occurrences past the horizon, which aren't saved and so can't be
+fn print_tasks(tasks: &[Task], subjects: &[Subject], indices: &[usize], numbered: usize) {
+    if indices.is_empty() {
+        println!("nothing to show");
+        return;
+    }
+    let name_width = indices.iter().map(|&i| text::width(&subject_name(subjects, tasks[i].subject))).max().unwrap_or(0);
+    let number_width = indices.iter().map(|&i| (i + 1).to_string().len()).max().unwrap_or(1);
+    let time = |task: &Task| task.time.map(|time| format!(" {}", dates::format_time(time))).unwrap_or_default();
+    let time_width = indices.iter().map(|&i| time(&tasks[i]).len()).max().unwrap_or(0);
+    for &i in indices {
+        let task = &tasks[i];
+        println!(
+            "{:>number_width$}  {}{:>time_width$}  {}  {}{}{}",
+            if i < numbered { (i + 1).to_string() } else { String::new() },
+            task.date.format("%a %Y-%m-%d"),
+            time(task),
+            text::pad(&subject_name(subjects, task.subject), name_width),
+            task.description,
+            if task.priority == Priority::Normal { String::new() } else { format!("  ({})", task.priority.name()) },
+            if task.completed.is_some() { "  (done)" } else { "" },
+        );
+    }
+}
+
+fn sorted_by_date(tasks: &[Task], keep: impl Fn(&Task) -> bool) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..tasks.len()).filter(|&i| keep(&tasks[i])).collect();
+    indices.sort_by_key(|&i| tasks[i].due());
+    return indices;
+}
+
+fn parse_number(tasks: &[Task], text: Option<&String>) -> Result<usize> {
+    let text = text.ok_or_else(|| usage_error("which task? give its number from list"))?;
+    return match text.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= tasks.len() => Ok(number - 1),
+        _ => Err(Error::new(ErrorKind::InvalidInput, format!("there's no task number {text}, see list"))),
+    };
+}
+
+fn parse_date(text: &str, today: NaiveDate) -> Result<NaiveDate> {
+    return dates::parse_date(text, today).map_err(|error| Error::new(ErrorKind::InvalidInput, error));
+}
+
+// dates like "next monday" span several arguments, so take the longest run of words that reads as
+// a date and leave the rest for the description. the longest form is "3 days from now"
+fn split_date(args: &[String], today: NaiveDate) -> Result<(NaiveDate, String)> {
+    for words in (1..=4.min(args.len() - 1)).rev() {
+        if let Ok(date) = dates::parse_date(&args[..words].join(" "), today) {
+            return Ok((date, args[words..].join(" ")));
+        }
+    }
+    return parse_date(&args[0], today).map(|date| (date, args[1..].join(" ")));
+}
+
+// an optional time right after the date, as in "add stats fri 11:59pm essay"
+fn split_time(description: String) -> (Option<NaiveTime>, String) {
+    if let Some((first, rest)) = description.split_once(' ') && let Ok(time) = dates::parse_time(first) {
+        return (Some(time), rest.to_string());
+    }
+    return (None, description);
+}
+
+// pulls `--<name> <value>` (or `--<name>=<value>`) out of the arguments, wherever it is
+fn take_flag(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    let flag = format!("--{name}");
+    let Some(i) = args.iter().position(|arg| *arg == flag || arg.starts_with(&format!("{flag}="))) else {
+        return Ok(None);
+    };
+    let arg = args.remove(i);
+    if let Some(value) = arg.strip_prefix(&format!("{flag}=")) {
+        return Ok(Some(value.to_string()));
+    }
+    if i >= args.len() {
+        return Err(usage_error(&format!("{flag} needs a value")));
+    }
+    return Ok(Some(args.remove(i)));
+}
+
+// series occurrences only exist once something asks for them, and task numbers have to stay put
+// between one command and the next, so anything generated up to the horizon is saved straight away
+fn expand(path: &Path, tasks: &mut Vec<Task>, series: &[Series], history: &History, last: NaiveDate) -> Result<()> {
+    if recurrence::expand(series, tasks, last) {
+        storage::save(path, tasks, series, history)?;
+    }
+    return Ok(());
+}
+
+// changes made here go into the history too, so u in the app can take them back
+pub fn run(args: &[String], path: &Path, (mut tasks, mut series, mut history): (Vec<Task>, Vec<Series>, History), subjects: &[Subject]) -> Result<()> {
+    if matches!(args[0].as_str(), "help" | "-h" | "--help") {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    let today = chrono::Local::now().date_naive();
+    let horizon = recurrence::horizon(today);
+    expand(path, &mut tasks, &series, &history, horizon)?;
+
+    let (change, message) = match args[0].as_str() {
+        "list" | "ls" => {
+            let all = match args.get(1).map(|arg| arg.as_str()) {
+                Some("--all" | "-a") => true,
+                Some(other) => return Err(usage_error(&format!("list doesn't take \"{other}\""))),
+                None => false,
+            };
+            print_tasks(&tasks, subjects, &sorted_by_date(&tasks, |task| all || task.completed.is_none()), tasks.len());
+            return Ok(());
+        },
+        "due" => {
+            let date = match args.get(1) {
+                Some(_) => parse_date(&args[1..].join(" "), today)?,
+                None => today,
+            };
+            let numbered = tasks.len();
+            recurrence::expand(&series, &mut tasks, date);
+            print_tasks(&tasks, subjects, &sorted_by_date(&tasks, |task| task.completed.is_none() && task.date <= date), numbered);
+            return Ok(());
+        },
+        "add" => {
+            let mut args = args.to_vec();
+            let repeat = take_flag(&mut args, "repeat")?;
+            let priority = match take_flag(&mut args, "priority")? {
+                Some(text) => Priority::parse(&text)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("\"{text}\" isn't low, normal, high or urgent")))?,
+                None => Priority::Normal,
+            };
+            if args.len() < 4 {
+                return Err(usage_error("add needs a subject, a date and a description"));
+            }
+            let subject = subjects::lookup(subjects, &args[1]).ok_or_else(|| {
+                let known: Vec<&str> = subjects.iter().filter(|subject| !subject.archived).map(|subject| subject.name.as_str()).collect();
+                Error::new(ErrorKind::InvalidInput, format!("unknown subject \"{}\", try one of: {}", args[1], known.join(", ")))
+            })?;
+            let (date, description) = split_date(&args[2..], today)?;
+            let (time, description) = split_time(description);
+            let at = time.map(|time| format!(" at {}", dates::format_time(time))).unwrap_or_default();
+            match repeat {
+                Some(rule) => {
+                    let recurrence = recurrence::parse(&rule, date, today).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+                    let message = format!("added {} {}{at}: {}", subject_name(subjects, subject), recurrence.format(), description);
+                    let id = recurrence::next_id(&series, &tasks);
+                    (Change::AddSeries(Series { id, subject, description, time, priority, start: date, recurrence, skipped: Vec::new() }), message)
+                },
+                None => {
+                    let message = format!("added {} for {}{at}: {}", subject_name(subjects, subject), date.format("%a %b %-d"), description);
+                    (Change::Add(Task { subject, description, date, time, completed: None, priority, occurrence: None }), message)
+                },
+            }
+        },
+        "done" => {
+            let index = parse_number(&tasks, args.get(1))?;
+            if tasks[index].completed.is_some() {
+                println!("\"{}\" was already done", tasks[index].description);
+                return Ok(());
+            }
+            (Change::ToggleCompleted(index), format!("finished \"{}\"", tasks[index].description))
+        },
+        "rm" | "remove" => {
+            let index = parse_number(&tasks, args.get(1))?;
+            let task = &tasks[index];
+            let message = match task.occurrence.filter(|(id, _)| series.iter().any(|series| series.id == *id)) {
+                Some(_) => format!("skipped \"{}\" on {}", task.description, task.date.format("%a %b %-d")),
+                None => format!("deleted \"{}\"", task.description),
+            };
+            (Change::Delete(index), message)
+        },
+        "stop" => {
+            let index = parse_number(&tasks, args.get(1))?;
+            let Some(id) = tasks[index].occurrence.map(|(id, _)| id).filter(|id| series.iter().any(|series| series.id == *id)) else {
+                return Err(Error::new(ErrorKind::InvalidInput, format!("\"{}\" doesn't repeat", tasks[index].description)));
+            };
+            (Change::EndSeries(id), format!("stopped repeating \"{}\"", tasks[index].description))
+        },
+        other => return Err(usage_error(&format!("unknown command \"{other}\""))),
+    };
+
+    let step = Step::record(change, &mut tasks, &mut series);
+    recurrence::expand(&series, &mut tasks, horizon);
+    history.push(vec![step]);
+    storage::save(path, &tasks, &series, &history)?;
+    println!("{message}");
+    return Ok(());
+}
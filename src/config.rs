@@ -0,0 +1,124 @@
+use chrono::Weekday;
+use std::{
+    fs,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+use crate::{dates, palette::{self, ColorSupport}, storage::{self, invalid}, subjects, theme::{self, Background, Theme}};
+
+// config.txt is "key = value" lines, and lines starting with # are comments. unknown keys are errors
+// so a typo doesn't silently do nothing
+pub struct Config {
+    pub week_start: Weekday,
+    pub sort: [SortOrder; 3], // for the today, week and month tabs, in that order
+    pub background: Background, // anything but the wave costs nothing to keep on screen
+    pub stats: bool,   // shows what each frame cost to draw in the bottom border
+    pub theme: Theme,  // named in the file, T in the app steps through the others
+    pub colors: ColorSupport, // detected from the environment unless the file says otherwise
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        return Config {
+            week_start: Weekday::Sun,
+            sort: [SortOrder::Date, SortOrder::Date, SortOrder::Date],
+            background: Background::Wave { speed: 1., amplitude: 1. },
+            stats: false,
+            theme: Theme::dark(),
+            colors: palette::detect(),
+        };
+    }
+}
+
+impl Config {
+    // o in the app steps the current tab through the orders without touching the file
+    pub fn cycle_sort(&mut self, tab: usize) {
+        self.sort[tab] = match self.sort[tab] {
+            SortOrder::Date => SortOrder::Priority,
+            SortOrder::Priority => SortOrder::Subject,
+            SortOrder::Subject => SortOrder::Date,
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Date,     // by due date and time
+    Priority, // most important first, then by date
+    Subject,  // in the order of the subjects file, then by date
+}
+
+impl SortOrder {
+    pub fn name(self) -> &'static str {
+        return match self {
+            SortOrder::Date => "date",
+            SortOrder::Priority => "priority",
+            SortOrder::Subject => "subject",
+        };
+    }
+
+    fn parse(text: &str) -> Option<SortOrder> {
+        return match text.trim().to_lowercase().as_str() {
+            "date" => Some(SortOrder::Date),
+            "priority" => Some(SortOrder::Priority),
+            "subject" => Some(SortOrder::Subject),
+            _ => None,
+        };
+    }
+}
+
+pub fn default_path() -> PathBuf {
+    return subjects::config_dir().join("config.txt");
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    return match text.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" => Some(false),
+        _ => None,
+    };
+}
+
+pub fn load(path: &Path) -> Result<Config> {
+    let mut config = Config::default();
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(config),
+        Err(error) => return Err(error),
+    };
+
+    // the wave's settings can come before or after the line choosing it
+    let mut wave = (1., 1.);
+    for setting in storage::settings(&contents, path) {
+        let (line, key, value) = setting?;
+        match key {
+            "week_start" => config.week_start = dates::parse_weekday(value)
+                .ok_or_else(|| invalid(path, line, &format!("\"{value}\" is not a day of the week")))?,
+            key @ ("today_sort" | "week_sort" | "month_sort") => {
+                let tab = ["today_sort", "week_sort", "month_sort"].iter().position(|name| *name == key).expect("matched above");
+                config.sort[tab] = SortOrder::parse(value)
+                    .ok_or_else(|| invalid(path, line, &format!("\"{value}\" is not date, priority or subject")))?;
+            },
+            "stats" => config.stats = parse_bool(value).ok_or_else(|| invalid(path, line, &format!("\"{value}\" is not true or false")))?,
+            "background" => config.background = Background::parse(value)
+                .ok_or_else(|| invalid(path, line, &format!("\"{value}\" is not wave, gradient, solid or off")))?,
+            key @ ("wave_speed" | "wave_amplitude") => {
+                let number = value.parse::<f64>().ok().filter(|number| number.is_finite() && *number >= 0.)
+                    .ok_or_else(|| invalid(path, line, &format!("\"{value}\" is not a number like 0.5")))?;
+                match key {
+                    "wave_speed" => wave.0 = number,
+                    _ => wave.1 = number,
+                }
+            },
+            "colors" => config.colors = ColorSupport::parse(value)
+                .ok_or_else(|| invalid(path, line, &format!("\"{value}\" is not auto, truecolor, 256 or 16")))?
+                .unwrap_or_else(palette::detect),
+            "theme" => config.theme = theme::load(value).map_err(|error| invalid(path, line, &error.to_string()))?,
+            other => return Err(invalid(path, line, &format!("unknown setting \"{other}\""))),
+        }
+    }
+    if let Background::Wave { speed, amplitude } = &mut config.background {
+        (*speed, *amplitude) = wave;
+    }
+    return Ok(config);
+}
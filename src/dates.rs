@@ -0,0 +1,293 @@
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveTime, Weekday};
+
+// turns what people actually type into a due date field ("fri", "in 3 days", "9/4") into a date.
+// weekdays follow GNU date: "fri" is today if today is a friday, "next fri" is always after today
+const HINT: &str = "try \"tomorrow\", \"fri\", \"next monday\", \"in 3 days\", \"9/4\" or \"2025-09-04\"";
+
+pub fn parse_weekday(text: &str) -> Option<Weekday> {
+    return match text.trim().to_lowercase().as_str() {
+        "sun" | "sunday" => Some(Weekday::Sun),
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "weds" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        _ => None,
+    };
+}
+
+fn add_days(date: NaiveDate, days: u64) -> Result<NaiveDate, String> {
+    return date.checked_add_days(Days::new(days)).ok_or("that's too far in the future".to_string());
+}
+
+// the first `weekday` on or after `today`
+fn upcoming(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = weekday.days_since(today.weekday());
+    return today.checked_add_days(Days::new(days as u64)).unwrap_or(today);
+}
+
+fn parse_offset(count: &str, unit: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let count = match count {
+        "a" | "an" | "one" => 1,
+        _ => count.parse::<u32>().map_err(|_| format!("\"{count}\" isn't a number"))?,
+    };
+    return match unit.trim_end_matches('s') {
+        "day" | "d" => add_days(today, count as u64),
+        "week" | "wk" | "w" => add_days(today, count as u64 * 7),
+        "month" | "mo" => today.checked_add_months(Months::new(count)).ok_or("that's too far in the future".to_string()),
+        _ => Err(format!("\"{unit}\" isn't days, weeks or months")),
+    };
+}
+
+// a month and day without a year lands in whichever year puts it closest to today,
+// so "1/10" typed in december means next january and "10/17" typed on the 18th means yesterday
+fn closest_year(month: u32, day: u32, today: NaiveDate) -> Option<NaiveDate> {
+    return [today.year() - 1, today.year(), today.year() + 1].into_iter()
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .min_by_key(|date| (*date - today).num_days().abs());
+}
+
+fn parse_numeric(text: &str, today: NaiveDate) -> Option<Result<NaiveDate, String>> {
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Some(Ok(date));
+    }
+    let parts: Vec<&str> = text.split('/').collect();
+    if parts.len() < 2 || parts.len() > 3 || parts.iter().any(|part| part.is_empty() || !part.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let month = parts[0].parse::<u32>().ok()?;
+    let day = parts[1].parse::<u32>().ok()?;
+    let date = match parts.get(2) {
+        Some(year) => {
+            let mut year = year.parse::<i32>().ok()?;
+            if year < 100 {
+                year += 2000;
+            }
+            NaiveDate::from_ymd_opt(year, month, day)
+        },
+        None => closest_year(month, day, today),
+    };
+    return Some(date.ok_or(format!("{text} isn't a real date")));
+}
+
+pub fn parse_date(text: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let text = text.trim().to_lowercase();
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return Err(format!("enter a due date, {HINT}"));
+    }
+
+    let date = match words.as_slice() {
+        ["today" | "tod" | "now"] => Ok(today),
+        ["tomorrow" | "tmr" | "tmrw" | "tomorow"] => add_days(today, 1),
+        ["yesterday"] => today.checked_sub_days(Days::new(1)).ok_or("that's too far in the past".to_string()),
+        ["next", "week"] => add_days(today, 7),
+        ["next", "month"] => today.checked_add_months(Months::new(1)).ok_or("that's too far in the future".to_string()),
+        ["in", count, unit] => parse_offset(count, unit, today),
+        [count, unit, "from", "now"] => parse_offset(count, unit, today),
+        ["next", day] => match parse_weekday(day) {
+            Some(weekday) => add_days(upcoming(today, weekday), if weekday == today.weekday() { 7 } else { 0 }),
+            None => Err(format!("\"{day}\" isn't a day of the week")),
+        },
+        ["this", day] | [day] if parse_weekday(day).is_some() => Ok(upcoming(today, parse_weekday(day).expect("checked by the guard"))),
+        [single] => match parse_numeric(single, today) {
+            Some(date) => date,
+            None => Err(format!("\"{text}\" isn't a date I understand, {HINT}")),
+        },
+        _ => Err(format!("\"{text}\" isn't a date I understand, {HINT}")),
+    };
+    return date;
+}
+
+// times need a colon or am/pm ("3pm", "15:30", "11:59 pm") so a description starting with a
+// number isn't mistaken for one
+pub fn parse_time(text: &str) -> Result<NaiveTime, String> {
+    let text: String = text.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    if text == "noon" {
+        return Ok(NaiveTime::from_hms_opt(12, 0, 0).expect("noon exists"));
+    }
+    let invalid = || format!("\"{text}\" isn't a time, try \"3pm\", \"11:59pm\" or \"15:30\"");
+    let (clock, meridiem) = match text.strip_suffix("am").or(text.strip_suffix('a')) {
+        Some(clock) => (clock, Some(0)),
+        None => match text.strip_suffix("pm").or(text.strip_suffix('p')) {
+            Some(clock) => (clock, Some(12)),
+            None => (text.as_str(), None),
+        },
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour, minute),
+        Some(_) => return Err(invalid()),
+        None if meridiem.is_some() => (clock, "00"),
+        None => return Err(invalid()),
+    };
+    let (Ok(hour), Ok(minute)) = (hour.parse::<u32>(), minute.parse::<u32>()) else {
+        return Err(invalid());
+    };
+    let hour = match meridiem {
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return Err(format!("{hour} isn't an hour on a 12 hour clock")),
+        None => hour,
+    };
+    return NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid);
+}
+
+pub fn format_time(time: NaiveTime) -> String {
+    return time.format("%-I:%M%P").to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a wednesday
+    fn today() -> NaiveDate {
+        return NaiveDate::from_ymd_opt(2025, 9, 3).unwrap();
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    }
+
+    fn parse(text: &str) -> Result<NaiveDate, String> {
+        return parse_date(text, today());
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        return NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+    }
+
+    #[test]
+    fn relative_words() {
+        assert_eq!(parse("today"), Ok(date(2025, 9, 3)));
+        assert_eq!(parse("tomorrow"), Ok(date(2025, 9, 4)));
+        assert_eq!(parse("tmrw"), Ok(date(2025, 9, 4)));
+        assert_eq!(parse("yesterday"), Ok(date(2025, 9, 2)));
+        assert_eq!(parse("next week"), Ok(date(2025, 9, 10)));
+        assert_eq!(parse("next month"), Ok(date(2025, 10, 3)));
+    }
+
+    #[test]
+    fn ignores_case_and_spacing() {
+        assert_eq!(parse("  Tomorrow "), Ok(date(2025, 9, 4)));
+        assert_eq!(parse("NEXT   Monday"), Ok(date(2025, 9, 8)));
+        assert_eq!(parse("In 3 Days"), Ok(date(2025, 9, 6)));
+    }
+
+    #[test]
+    fn bare_weekdays_are_the_upcoming_one() {
+        assert_eq!(parse("fri"), Ok(date(2025, 9, 5)));
+        assert_eq!(parse("friday"), Ok(date(2025, 9, 5)));
+        assert_eq!(parse("this friday"), Ok(date(2025, 9, 5)));
+        assert_eq!(parse("mon"), Ok(date(2025, 9, 8)));
+        assert_eq!(parse("tues"), Ok(date(2025, 9, 9)));
+        assert_eq!(parse("thurs"), Ok(date(2025, 9, 4)));
+    }
+
+    #[test]
+    fn todays_weekday_means_today_unless_next() {
+        assert_eq!(parse("wed"), Ok(date(2025, 9, 3)));
+        assert_eq!(parse("next wed"), Ok(date(2025, 9, 10)));
+    }
+
+    #[test]
+    fn next_weekday_is_the_first_one_after_today() {
+        assert_eq!(parse("next monday"), Ok(date(2025, 9, 8)));
+        assert_eq!(parse("next fri"), Ok(date(2025, 9, 5)));
+        assert_eq!(parse("next tue"), Ok(date(2025, 9, 9)));
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse("in 3 days"), Ok(date(2025, 9, 6)));
+        assert_eq!(parse("in 1 day"), Ok(date(2025, 9, 4)));
+        assert_eq!(parse("in a week"), Ok(date(2025, 9, 10)));
+        assert_eq!(parse("in 2 weeks"), Ok(date(2025, 9, 17)));
+        assert_eq!(parse("in 1 month"), Ok(date(2025, 10, 3)));
+        assert_eq!(parse("in 0 days"), Ok(date(2025, 9, 3)));
+        assert_eq!(parse("5 days from now"), Ok(date(2025, 9, 8)));
+    }
+
+    #[test]
+    fn month_offsets_clamp_to_the_end_of_short_months() {
+        assert_eq!(parse_date("in 1 month", date(2025, 1, 31)), Ok(date(2025, 2, 28)));
+    }
+
+    #[test]
+    fn numeric_dates() {
+        assert_eq!(parse("2025-09-04"), Ok(date(2025, 9, 4)));
+        assert_eq!(parse("9/4"), Ok(date(2025, 9, 4)));
+        assert_eq!(parse("09/04"), Ok(date(2025, 9, 4)));
+        assert_eq!(parse("9/4/2026"), Ok(date(2026, 9, 4)));
+        assert_eq!(parse("9/4/26"), Ok(date(2026, 9, 4)));
+    }
+
+    #[test]
+    fn month_and_day_pick_the_closest_year() {
+        assert_eq!(parse_date("1/10", date(2025, 12, 15)), Ok(date(2026, 1, 10)));
+        assert_eq!(parse_date("12/20", date(2026, 1, 5)), Ok(date(2025, 12, 20)));
+        assert_eq!(parse_date("10/17", date(2025, 10, 18)), Ok(date(2025, 10, 17)));
+    }
+
+    #[test]
+    fn leap_days_only_exist_in_leap_years() {
+        assert_eq!(parse_date("2/29", date(2028, 2, 1)), Ok(date(2028, 2, 29)));
+        assert!(parse("2/29/2025").is_err());
+    }
+
+    #[test]
+    fn rejects_impossible_dates() {
+        assert_eq!(parse("9/31"), Err("9/31 isn't a real date".to_string()));
+        assert!(parse("13/1").is_err());
+        assert!(parse("2025-02-30").is_err());
+    }
+
+    #[test]
+    fn rejects_gibberish_with_a_hint() {
+        let error = parse("someday").unwrap_err();
+        assert!(error.contains("someday"));
+        assert!(error.contains(HINT));
+        assert!(parse("").unwrap_err().contains(HINT));
+        assert!(parse("9/4/").is_err());
+        assert!(parse("in three days").is_err());
+        assert!(parse("in 3 fortnights").is_err());
+        assert!(parse("next blursday").is_err());
+    }
+
+    #[test]
+    fn twelve_hour_times() {
+        assert_eq!(parse_time("3pm"), Ok(time(15, 0)));
+        assert_eq!(parse_time("3 PM"), Ok(time(15, 0)));
+        assert_eq!(parse_time("11:59pm"), Ok(time(23, 59)));
+        assert_eq!(parse_time("9:05a"), Ok(time(9, 5)));
+        assert_eq!(parse_time("12am"), Ok(time(0, 0)));
+        assert_eq!(parse_time("12pm"), Ok(time(12, 0)));
+        assert_eq!(parse_time("noon"), Ok(time(12, 0)));
+    }
+
+    #[test]
+    fn twenty_four_hour_times() {
+        assert_eq!(parse_time("15:30"), Ok(time(15, 30)));
+        assert_eq!(parse_time("0:00"), Ok(time(0, 0)));
+        assert_eq!(parse_time("23:59"), Ok(time(23, 59)));
+    }
+
+    #[test]
+    fn rejects_bad_times() {
+        assert!(parse_time("3").is_err());
+        assert!(parse_time("13pm").is_err());
+        assert!(parse_time("0am").is_err());
+        assert!(parse_time("24:00").is_err());
+        assert!(parse_time("9:5").is_err());
+        assert!(parse_time("9:60").is_err());
+        assert!(parse_time("soon").is_err());
+        assert!(parse_time("").is_err());
+    }
+
+    #[test]
+    fn times_format_on_a_twelve_hour_clock() {
+        assert_eq!(format_time(time(23, 59)), "11:59pm");
+        assert_eq!(format_time(time(9, 5)), "9:05am");
+        assert_eq!(format_time(time(0, 0)), "12:00am");
+    }
+}
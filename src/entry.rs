@@ -0,0 +1,327 @@
+use chrono::NaiveDate;
+use crossterm::event::{KeyCode, KeyEvent};
+use crate::{Priority, Task, dates, recurrence::{self, Recurrence, Series}, subjects::Subject, text};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, Default)]
+pub struct TextField {
+    pub text: String,
+    pub cursor: usize, // a byte offset, always on a grapheme boundary
+}
+
+impl TextField {
+    pub fn new(text: &str) -> TextField {
+        return TextField { text: text.to_string(), cursor: text.len() };
+    }
+
+    // where the grapheme before the cursor starts
+    fn previous(&self) -> usize {
+        return self.text[..self.cursor].grapheme_indices(true).next_back().map_or(0, |(i, _)| i);
+    }
+
+    // where the grapheme after the cursor ends
+    fn next(&self) -> usize {
+        return self.cursor + self.text[self.cursor..].graphemes(true).next().map_or(0, str::len);
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        // a character can also join onto what comes after it, like the first half of a flag, and
+        // then the cursor goes past the whole grapheme rather than into it
+        if let Some((i, grapheme)) = self.text.grapheme_indices(true).find(|&(i, grapheme)| i < self.cursor && self.cursor < i + grapheme.len()) {
+            self.cursor = i + grapheme.len();
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        let start = self.previous();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn delete(&mut self) {
+        let end = self.next();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    // returns false for keys that aren't text editing so the caller can use them
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.cursor = self.previous(),
+            KeyCode::Right => self.cursor = self.next(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => return false,
+        }
+        return true;
+    }
+
+    // the slice of text that fits in `width` cells while keeping the cursor visible,
+    // along with the cell the cursor lands on inside that slice
+    pub fn visible(&self, width: usize) -> (String, usize) {
+        let (before, after) = self.text.split_at(self.cursor);
+        // drop whole graphemes off the front until there's a cell left for the cursor
+        let mut before = before;
+        while !before.is_empty() && text::width(before) >= width {
+            let first = before.graphemes(true).next().map_or(0, |grapheme| grapheme.len());
+            before = &before[first..];
+        }
+        let cursor = text::width(before);
+        return (format!("{before}{}", text::fit(after, width.saturating_sub(cursor))), cursor);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EntryField {
+    Subject,
+    Description,
+    Date,
+    Time,
+    Repeat,
+    Priority,
+}
+
+pub struct EntryForm {
+    pub field: EntryField,
+    pub subject: u32, // a Subject::id
+    pub description: TextField,
+    pub date: TextField,
+    pub time: TextField, // optional, empty means any time that day
+    pub repeat: TextField, // optional, empty means just once
+    pub priority: Priority,
+    pub series: Option<String>, // the rule of the series an edited occurrence belongs to, which the form leaves alone
+    pub error: Option<String>,
+    pub notice: Option<String>,
+    pub editing: Option<usize>, // index into App::tasks when the form is changing an existing task
+}
+
+impl EntryForm {
+    pub fn new(today: NaiveDate, subjects: &[Subject]) -> EntryForm {
+        return EntryForm {
+            field: EntryField::Subject,
+            subject: subjects.iter().find(|subject| !subject.archived).map(|subject| subject.id).unwrap_or(0),
+            description: TextField::default(),
+            date: TextField::new(&today.format("%Y-%m-%d").to_string()),
+            time: TextField::default(),
+            repeat: TextField::default(),
+            priority: Priority::Normal,
+            series: None,
+            error: None,
+            notice: None,
+            editing: None,
+        };
+    }
+
+    pub fn edit(&mut self, index: usize, task: &Task, series: Option<&Series>) {
+        self.field = EntryField::Description;
+        self.subject = task.subject;
+        self.description = TextField::new(&task.description);
+        self.date = TextField::new(&task.date.format("%Y-%m-%d").to_string());
+        self.time = TextField::new(&task.time.map(dates::format_time).unwrap_or_default());
+        self.repeat = TextField::default();
+        self.priority = task.priority;
+        self.series = series.map(|series| series.recurrence.format());
+        self.error = None;
+        self.notice = None;
+        self.editing = Some(index);
+    }
+
+    // steps through the subjects that aren't archived, wrapping around at either end
+    fn cycle_subject(&mut self, subjects: &[Subject], forward: bool) {
+        let active: Vec<u32> = subjects.iter().filter(|subject| !subject.archived).map(|subject| subject.id).collect();
+        if active.is_empty() {
+            return;
+        }
+        self.subject = match active.iter().position(|&id| id == self.subject) {
+            Some(i) if forward => active[(i + 1) % active.len()],
+            Some(i) => active[(i + active.len() - 1) % active.len()],
+            None => active[0],
+        };
+    }
+
+    fn next_field(&mut self) {
+        self.field = match self.field {
+            EntryField::Subject => EntryField::Description,
+            EntryField::Description => EntryField::Date,
+            EntryField::Date => EntryField::Time,
+            EntryField::Time if self.series.is_some() => EntryField::Priority,
+            EntryField::Time => EntryField::Repeat,
+            EntryField::Repeat => EntryField::Priority,
+            EntryField::Priority => EntryField::Priority,
+        };
+    }
+
+    fn previous_field(&mut self) {
+        self.field = match self.field {
+            EntryField::Subject => EntryField::Subject,
+            EntryField::Description => EntryField::Subject,
+            EntryField::Date => EntryField::Description,
+            EntryField::Time => EntryField::Date,
+            EntryField::Repeat => EntryField::Time,
+            EntryField::Priority if self.series.is_some() => EntryField::Time,
+            EntryField::Priority => EntryField::Repeat,
+        };
+    }
+
+    // returns a finished task once enter is pressed on a valid form, and how it repeats if it does
+    pub fn handle_key(&mut self, key: KeyEvent, subjects: &[Subject], today: NaiveDate) -> Option<(Task, Option<Recurrence>)> {
+        self.notice = None;
+        match key.code {
+            KeyCode::Up => self.previous_field(),
+            KeyCode::Down => self.next_field(),
+            KeyCode::Enter => return self.submit(today),
+            _ => match self.field {
+                EntryField::Subject => match key.code {
+                    KeyCode::Left => self.cycle_subject(subjects, false),
+                    KeyCode::Right | KeyCode::Char(' ') => self.cycle_subject(subjects, true),
+                    _ => {}
+                },
+                EntryField::Description => { self.description.handle_key(key); },
+                EntryField::Date => {
+                    if self.date.handle_key(key) {
+                        self.error = None;
+                    }
+                },
+                EntryField::Time => {
+                    if self.time.handle_key(key) {
+                        self.error = None;
+                    }
+                },
+                EntryField::Repeat => {
+                    if self.repeat.handle_key(key) {
+                        self.error = None;
+                    }
+                },
+                EntryField::Priority => match key.code {
+                    KeyCode::Left => self.priority = self.priority.lowered(),
+                    KeyCode::Right | KeyCode::Char(' ') => self.priority = self.priority.raised(),
+                    _ => {}
+                },
+            },
+        }
+        return None;
+    }
+
+    fn submit(&mut self, today: NaiveDate) -> Option<(Task, Option<Recurrence>)> {
+        let description = self.description.text.trim();
+        if description.is_empty() {
+            self.error = Some("the description can't be empty".to_string());
+            self.field = EntryField::Description;
+            return None;
+        }
+        let date = match dates::parse_date(&self.date.text, today) {
+            Ok(date) => date,
+            Err(error) => {
+                self.error = Some(error);
+                self.field = EntryField::Date;
+                return None;
+            }
+        };
+        let time = match self.time.text.trim() {
+            "" => None,
+            text => match dates::parse_time(text) {
+                Ok(time) => Some(time),
+                Err(error) => {
+                    self.error = Some(error);
+                    self.field = EntryField::Time;
+                    return None;
+                }
+            },
+        };
+
+        let recurrence = match self.repeat.text.trim() {
+            "" => None,
+            _ if self.series.is_some() => None,
+            text => match recurrence::parse(text, date, today) {
+                Ok(recurrence) => Some(recurrence),
+                Err(error) => {
+                    self.error = Some(error);
+                    self.field = EntryField::Repeat;
+                    return None;
+                }
+            },
+        };
+
+        let task = Task { subject: self.subject, description: description.to_string(), date, time, completed: None, priority: self.priority, occurrence: None };
+        // keep everything but the description around, homework tends to come in batches
+        self.description = TextField::default();
+        self.field = EntryField::Description;
+        self.error = None;
+        let at = task.time.map(|time| format!(" at {}", dates::format_time(time))).unwrap_or_default();
+        self.notice = Some(match &recurrence {
+            Some(recurrence) => format!("added \"{}\" {}{at}", task.description, recurrence.format()),
+            None => format!("added \"{}\" for {}{at}", task.description, task.date.format("%a %b %-d")),
+        });
+        return Some((task, recurrence));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn press(field: &mut TextField, code: KeyCode) {
+        assert!(field.handle_key(KeyEvent::new(code, KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn moving_by_grapheme() {
+        let mut field = TextField::new("cafe\u{301} 👩‍💻");
+        press(&mut field, KeyCode::Left);
+        assert_eq!(field.cursor, "cafe\u{301} ".len());
+        press(&mut field, KeyCode::Left);
+        press(&mut field, KeyCode::Left);
+        assert_eq!(field.cursor, "caf".len());
+        press(&mut field, KeyCode::Right);
+        assert_eq!(field.cursor, "cafe\u{301}".len());
+        press(&mut field, KeyCode::Home);
+        press(&mut field, KeyCode::Left);
+        assert_eq!(field.cursor, 0);
+        press(&mut field, KeyCode::End);
+        press(&mut field, KeyCode::Right);
+        assert_eq!(field.cursor, field.text.len());
+    }
+
+    #[test]
+    fn deleting_by_grapheme() {
+        let mut field = TextField::new("🇯🇵🇫🇷 cafe\u{301}");
+        press(&mut field, KeyCode::Backspace);
+        assert_eq!(field.text, "🇯🇵🇫🇷 caf");
+        press(&mut field, KeyCode::Home);
+        press(&mut field, KeyCode::Delete);
+        assert_eq!(field.text, "🇫🇷 caf");
+        press(&mut field, KeyCode::Right);
+        press(&mut field, KeyCode::Backspace);
+        assert_eq!((field.text.as_str(), field.cursor), (" caf", 0));
+        press(&mut field, KeyCode::Backspace);
+        assert_eq!(field.text, " caf");
+    }
+
+    #[test]
+    fn inserting() {
+        let mut field = TextField::new("cafe");
+        press(&mut field, KeyCode::Char('\u{301}'));
+        press(&mut field, KeyCode::Char('!'));
+        assert_eq!((field.text.as_str(), field.cursor), ("cafe\u{301}!", "cafe\u{301}!".len()));
+        // half a flag in front of a whole one pairs up with it, so the cursor skips the new flag
+        let mut field = TextField::new("\u{1f1eb}\u{1f1f7}");
+        press(&mut field, KeyCode::Home);
+        press(&mut field, KeyCode::Char('\u{1f1ef}'));
+        assert_eq!(field.cursor, "\u{1f1ef}\u{1f1eb}".len());
+        press(&mut field, KeyCode::Backspace);
+        assert_eq!(field.text, "\u{1f1f7}");
+    }
+
+    #[test]
+    fn scrolling() {
+        let mut field = TextField::new("日本語の宿題");
+        assert_eq!(field.visible(6), ("宿題".to_string(), 4));
+        press(&mut field, KeyCode::Home);
+        assert_eq!(field.visible(5), ("日本".to_string(), 0));
+    }
+}
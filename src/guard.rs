@@ -0,0 +1,66 @@
+use crossterm::{
+    cursor,
+    style::ResetColor,
+    terminal::{self, EndSynchronizedUpdate},
+    ExecutableCommand,
+};
+use signal_hook::{consts::{SIGHUP, SIGINT, SIGTERM}, iterator::Signals};
+use std::{
+    io::{stdout, Result},
+    panic, process,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+// everything the full screen app changes about the terminal is undone by restore, however it ends:
+// returning normally or with an error drops the Guard, a panic goes through the hook installed by
+// enter before its message is printed, and a signal is caught on its own thread
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+pub fn enter() -> Result<Guard> {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+    }));
+
+    let mut signals = Signals::new([SIGTERM, SIGHUP, SIGINT])?;
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            restore();
+            process::exit(128 + signal);
+        }
+    });
+
+    ACTIVE.store(true, Ordering::SeqCst);
+    let mut stdout = stdout();
+    stdout.execute(terminal::EnterAlternateScreen)?;
+    terminal::enable_raw_mode()?;
+    stdout.execute(cursor::Hide)?;
+    stdout.execute(terminal::DisableLineWrap)?;
+    return Ok(Guard);
+}
+
+// safe to call more than once, only the first does anything. errors are ignored since this runs
+// on the way out, where there's nobody left to report them to and each step is worth trying anyway
+pub fn restore() {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    let mut stdout = stdout();
+    // a frame cut off halfway may have left the terminal holding back output
+    let _ = stdout.execute(EndSynchronizedUpdate);
+    let _ = stdout.execute(ResetColor);
+    let _ = stdout.execute(terminal::EnableLineWrap);
+    let _ = stdout.execute(cursor::Show);
+    let _ = stdout.execute(terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
@@ -0,0 +1,301 @@
+use chrono::NaiveDate;
+use crate::{Change, Task, recurrence::Series};
+
+// undo and redo replay steps, which record where tasks and series were taken out of their lists and
+// where they were put in. undoing takes out what a step put in and puts back what it took out, and
+// redoing is the same thing the other way around. series occurrences are only ever generated onto
+// the end of the list, so positions recorded earlier stay good while they pile up behind them
+pub const LIMIT: usize = 50;
+
+#[derive(Clone)]
+pub struct Diff<T> {
+    pub removed: Vec<(usize, T)>, // positions before the step, ascending
+    pub added: Vec<(usize, T)>,   // positions after the step, ascending
+}
+
+impl<T> Default for Diff<T> {
+    fn default() -> Diff<T> {
+        return Diff { removed: Vec::new(), added: Vec::new() };
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Step {
+    pub tasks: Diff<Task>,
+    pub series: Diff<Series>,
+}
+
+impl Step {
+    // applies a change and records what it did
+    pub fn record(change: Change, tasks: &mut Vec<Task>, series: &mut Vec<Series>) -> Step {
+        let mut step = Step::default();
+        let touched_tasks = match &change {
+            Change::Edit(index, _) | Change::ToggleCompleted(index) | Change::Delete(index) => vec![*index],
+            Change::EndSeries(_) => change.removed(tasks),
+            Change::Add(_) | Change::AddSeries(_) => Vec::new(),
+        };
+        let touched_series: Vec<usize> = match &change {
+            Change::Delete(index) => match tasks[*index].occurrence {
+                Some((id, _)) => series.iter().position(|series| series.id == id).into_iter().collect(),
+                None => Vec::new(),
+            },
+            Change::EndSeries(id) => series.iter().position(|series| series.id == *id).into_iter().collect(),
+            _ => Vec::new(),
+        };
+        step.tasks.removed = touched_tasks.iter().map(|&i| (i, tasks[i].clone())).collect();
+        step.series.removed = touched_series.iter().map(|&i| (i, series[i].clone())).collect();
+
+        let (added_tasks, added_series) = match &change {
+            Change::Edit(index, _) | Change::ToggleCompleted(index) => (vec![*index], Vec::new()),
+            Change::Add(_) => (vec![tasks.len()], Vec::new()),
+            Change::Delete(_) => (Vec::new(), touched_series),
+            Change::AddSeries(_) => (Vec::new(), vec![series.len()]),
+            Change::EndSeries(_) => (Vec::new(), Vec::new()),
+        };
+        change.apply_to(tasks, series);
+        step.tasks.added = added_tasks.iter().map(|&i| (i, tasks[i].clone())).collect();
+        step.series.added = added_series.iter().map(|&i| (i, series[i].clone())).collect();
+        return step;
+    }
+}
+
+// takes `take` out of `list` and puts `put` in. positions are only a hint when finding what to take
+// out, since the cli or a hand edit may have moved things, but false (leaving `list` alone) if
+// something is missing altogether
+fn swap<T: Clone + PartialEq>(list: &mut Vec<T>, take: &[(usize, T)], put: &[(usize, T)]) -> bool {
+    let mut positions: Vec<usize> = Vec::new();
+    for (index, item) in take {
+        let found = match list.get(*index) {
+            Some(at) if at == item && !positions.contains(index) => Some(*index),
+            _ => (0..list.len()).find(|i| list[*i] == *item && !positions.contains(i)),
+        };
+        match found {
+            Some(i) => positions.push(i),
+            None => return false,
+        }
+    }
+    positions.sort_unstable_by(|a, b| b.cmp(a));
+    for i in positions {
+        list.remove(i);
+    }
+    for (index, item) in put {
+        list.insert((*index).min(list.len()), item.clone());
+    }
+    return true;
+}
+
+// each entry is a list of steps that were made together and are undone together, oldest first
+#[derive(Default)]
+pub struct History {
+    pub undo: Vec<Vec<Step>>,
+    pub redo: Vec<Vec<Step>>,
+}
+
+impl History {
+    // the latest date of any task the next undo (or redo) takes out or puts back. untouched
+    // occurrences past the horizon aren't saved, so they may need generating again before it fits
+    pub fn last_date(&self, redo: bool) -> Option<NaiveDate> {
+        let stack = if redo { &self.redo } else { &self.undo };
+        let steps = stack.last()?;
+        return steps.iter().flat_map(|step| step.tasks.removed.iter().chain(&step.tasks.added)).map(|(_, task)| task.date).max();
+    }
+
+    pub fn push(&mut self, steps: Vec<Step>) {
+        self.undo.push(steps);
+        if self.undo.len() > LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    // undoes the latest entry, returning the positions of the tasks it put back, or None if there's
+    // nothing to undo. an entry that no longer fits the task list throws the whole history away
+    pub fn undo(&mut self, tasks: &mut Vec<Task>, series: &mut Vec<Series>) -> Option<Vec<usize>> {
+        let steps = self.undo.pop()?;
+        let mut new_tasks = tasks.clone();
+        let mut new_series = series.clone();
+        for step in steps.iter().rev() {
+            if !swap(&mut new_tasks, &step.tasks.added, &step.tasks.removed) || !swap(&mut new_series, &step.series.added, &step.series.removed) {
+                *self = History::default();
+                return None;
+            }
+        }
+        let restored = steps.iter().flat_map(|step| step.tasks.removed.iter().map(|(i, _)| *i)).collect();
+        self.redo.push(steps);
+        finish(tasks, series, new_tasks, new_series);
+        return Some(restored);
+    }
+
+    pub fn redo(&mut self, tasks: &mut Vec<Task>, series: &mut Vec<Series>) -> Option<Vec<usize>> {
+        let steps = self.redo.pop()?;
+        let mut new_tasks = tasks.clone();
+        let mut new_series = series.clone();
+        for step in &steps {
+            if !swap(&mut new_tasks, &step.tasks.removed, &step.tasks.added) || !swap(&mut new_series, &step.series.removed, &step.series.added) {
+                *self = History::default();
+                return None;
+            }
+        }
+        let restored = steps.iter().flat_map(|step| step.tasks.added.iter().map(|(i, _)| *i)).collect();
+        self.undo.push(steps);
+        finish(tasks, series, new_tasks, new_series);
+        return Some(restored);
+    }
+}
+
+// occurrences generated after a series was added aren't part of any step, so when undo takes the
+// series away the unfinished ones would be left behind pointing at nothing. redoing it brings the
+// series back, and the occurrences are generated again from there
+fn finish(tasks: &mut Vec<Task>, series: &mut Vec<Series>, new_tasks: Vec<Task>, new_series: Vec<Series>) {
+    *tasks = new_tasks;
+    *series = new_series;
+    tasks.retain(|task| task.completed.is_some() || task.occurrence.is_none_or(|(id, _)| series.iter().any(|series| series.id == id)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Priority, recurrence};
+
+    fn date(day: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(2025, 9, day).unwrap();
+    }
+
+    fn task(description: &str) -> Task {
+        return Task {
+            subject: 1,
+            description: description.to_string(),
+            date: date(3),
+            time: None,
+            completed: None,
+            priority: Priority::Normal,
+            occurrence: None,
+        };
+    }
+
+    // a daily series starting on the 3rd, with its first week generated
+    fn daily() -> (Vec<Task>, Vec<Series>) {
+        let series = Series {
+            id: 1,
+            subject: 1,
+            description: "Reading".to_string(),
+            time: None,
+            priority: Priority::Normal,
+            start: date(3),
+            recurrence: recurrence::parse("daily", date(3), date(3)).unwrap(),
+            skipped: Vec::new(),
+        };
+        let mut tasks = vec![task("Homework")];
+        recurrence::expand(std::slice::from_ref(&series), &mut tasks, date(9));
+        return (tasks, vec![series]);
+    }
+
+    fn descriptions(tasks: &[Task]) -> Vec<&str> {
+        return tasks.iter().map(|task| task.description.as_str()).collect();
+    }
+
+    fn record(history: &mut History, change: Change, tasks: &mut Vec<Task>, series: &mut Vec<Series>) {
+        let step = Step::record(change, tasks, series);
+        history.push(vec![step]);
+    }
+
+    #[test]
+    fn add() {
+        let (mut tasks, mut series) = (vec![task("Homework")], Vec::new());
+        let mut history = History::default();
+        record(&mut history, Change::Add(task("Lab report")), &mut tasks, &mut series);
+
+        assert_eq!(history.undo(&mut tasks, &mut series), Some(Vec::new()));
+        assert_eq!(descriptions(&tasks), ["Homework"]);
+        assert_eq!(history.undo(&mut tasks, &mut series), None);
+        assert_eq!(history.redo(&mut tasks, &mut series), Some(vec![1]));
+        assert_eq!(descriptions(&tasks), ["Homework", "Lab report"]);
+        assert_eq!(history.redo(&mut tasks, &mut series), None);
+    }
+
+    #[test]
+    fn moved_tasks_are_found_anyway() {
+        let (mut tasks, mut series) = (vec![task("Homework")], Vec::new());
+        let mut history = History::default();
+        record(&mut history, Change::Add(task("Lab report")), &mut tasks, &mut series);
+        // as if the cli had put something in front of it since
+        tasks.insert(0, task("Essay"));
+
+        assert!(history.undo(&mut tasks, &mut series).is_some());
+        assert_eq!(descriptions(&tasks), ["Essay", "Homework"]);
+        // and it goes back in where it was first added
+        assert!(history.redo(&mut tasks, &mut series).is_some());
+        assert_eq!(descriptions(&tasks), ["Essay", "Lab report", "Homework"]);
+    }
+
+    #[test]
+    fn a_stale_entry_clears_the_history() {
+        let (mut tasks, mut series) = (vec![task("Homework")], Vec::new());
+        let mut history = History::default();
+        record(&mut history, Change::Add(task("Lab report")), &mut tasks, &mut series);
+        record(&mut history, Change::Add(task("Essay")), &mut tasks, &mut series);
+        tasks[2].description = "Essay, edited by hand".to_string();
+
+        assert_eq!(history.undo(&mut tasks, &mut series), None);
+        assert!(history.undo.is_empty() && history.redo.is_empty());
+        assert_eq!(descriptions(&tasks), ["Homework", "Lab report", "Essay, edited by hand"]);
+    }
+
+    #[test]
+    fn skipping_an_occurrence() {
+        let (mut tasks, mut series) = daily();
+        let mut history = History::default();
+        assert!(tasks[2].occurrence == Some((1, date(4))));
+        record(&mut history, Change::Delete(2), &mut tasks, &mut series);
+        assert_eq!(series[0].skipped, [date(4)]);
+        assert_eq!(tasks.len(), 7);
+
+        assert_eq!(history.undo(&mut tasks, &mut series), Some(vec![2]));
+        assert!(series[0].skipped.is_empty());
+        assert!(tasks[2].occurrence == Some((1, date(4))));
+        assert_eq!(tasks.len(), 8);
+
+        assert!(history.redo(&mut tasks, &mut series).is_some());
+        assert_eq!(series[0].skipped, [date(4)]);
+        assert!(tasks.iter().all(|task| task.date != date(4)));
+    }
+
+    #[test]
+    fn ending_a_series() {
+        let (mut tasks, mut series) = daily();
+        let mut history = History::default();
+        record(&mut history, Change::ToggleCompleted(1), &mut tasks, &mut series);
+        record(&mut history, Change::EndSeries(1), &mut tasks, &mut series);
+        // the finished occurrence stays behind as a record
+        assert!(series.is_empty());
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks[1].completed.is_some());
+
+        assert_eq!(history.undo(&mut tasks, &mut series), Some(vec![2, 3, 4, 5, 6, 7]));
+        assert_eq!(series.len(), 1);
+        assert_eq!(tasks.len(), 8);
+        assert!(history.redo(&mut tasks, &mut series).is_some());
+        assert!(series.is_empty());
+        assert_eq!(tasks.len(), 2);
+    }
+
+    #[test]
+    fn undoing_a_new_series_takes_its_occurrences() {
+        let (generated, mut added) = daily();
+        let (mut tasks, mut series) = (vec![task("Homework")], Vec::new());
+        let mut history = History::default();
+        record(&mut history, Change::AddSeries(added.remove(0)), &mut tasks, &mut series);
+        // generated after the step, so they aren't part of it
+        recurrence::expand(&series, &mut tasks, date(9));
+        tasks[3].completed = Some(date(5).and_hms_opt(12, 0, 0).unwrap());
+
+        assert!(history.undo(&mut tasks, &mut series).is_some());
+        assert!(series.is_empty());
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks[1].completed.is_some());
+
+        assert!(history.redo(&mut tasks, &mut series).is_some());
+        recurrence::expand(&series, &mut tasks, date(9));
+        assert_eq!(tasks.len(), generated.len());
+    }
+}
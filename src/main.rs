@@ -1,44 +1,162 @@
+#![allow(clippy::needless_return, clippy::needless_range_loop, clippy::identity_op)]
+
 use crossterm::{
     cursor, 
-    event::{poll, read, Event::Key, KeyCode, Event::Resize},
+    event::{poll, read, Event, Event::Key, KeyCode, KeyEventKind, KeyModifiers, Event::Resize},
     style::{self, Color, Stylize},
     terminal::{self, BeginSynchronizedUpdate, Clear, EndSynchronizedUpdate}, 
-    ExecutableCommand, QueueableCommand
+    QueueableCommand
 };
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use history::History;
+use recurrence::Series;
+use subjects::Subject;
 use std::{
-    io::{stdout, Result, Stdout}, time::{Duration, Instant}, vec
+    collections::HashMap, env, io::{stdout, Error, ErrorKind, Result, Stdout, Write}, path::PathBuf, process, time::{Duration, Instant, SystemTime}
 };
+use unicode_segmentation::UnicodeSegmentation;
 
+mod cli;
+mod config;
+mod dates;
+mod entry;
+mod guard;
+mod history;
+mod palette;
+mod recurrence;
+mod search;
+mod selection;
+mod storage;
+mod subjects;
 mod tabs;
+mod text;
+mod theme;
 
 const FRAMETIME: f64 = 1./12.;
+// below this the tabs and their borders don't fit, so a notice is shown instead
+const MIN_WIDTH: usize = 60;
+const MIN_HEIGHT: usize = 20;
 
-#[derive(Clone, Copy)]
-enum Subject {
-    Film,
-    Physics,
-    Stats,
-    APUSH,
-    Compsci,
-    Lang,
-    None,
+// every way self.tasks can change, applied in one place by App::apply
+enum Change {
+    Add(Task),
+    Edit(usize, Task),
+    Delete(usize),
+    ToggleCompleted(usize),
+    AddSeries(Series),
+    EndSeries(u32), // a Series::id
 }
 
-#[derive(Clone)]
+impl Change {
+    // indices into the task list that this change will remove
+    fn removed(&self, tasks: &[Task]) -> Vec<usize> {
+        return match self {
+            Change::Delete(index) => vec![*index],
+            Change::EndSeries(id) => (0..tasks.len()).filter(|&i| Change::ends_with_series(&tasks[i], *id)).collect(),
+            _ => Vec::new(),
+        };
+    }
+
+    // stopping a series takes its unfinished occurrences with it, finished ones stay as a record
+    fn ends_with_series(task: &Task, id: u32) -> bool {
+        return task.completed.is_none() && task.occurrence.is_some_and(|(series, _)| series == id);
+    }
+
+    // the bare edit to a task list, shared by the tui and the cli
+    fn apply_to(self, tasks: &mut Vec<Task>, series: &mut Vec<Series>) {
+        match self {
+            Change::Add(task) => tasks.push(task),
+            Change::Edit(index, task) => tasks[index] = task,
+            Change::Delete(index) => {
+                // deleting an occurrence skips that date rather than letting the series bring it back
+                let task = tasks.remove(index);
+                if let Some((id, date)) = task.occurrence && let Some(series) = series.iter_mut().find(|series| series.id == id) {
+                    series.skipped.push(date);
+                }
+            },
+            Change::AddSeries(new) => series.push(new),
+            Change::EndSeries(id) => {
+                series.retain(|series| series.id != id);
+                tasks.retain(|task| !Change::ends_with_series(task, id));
+            },
+            Change::ToggleCompleted(index) => {
+                let task = &mut tasks[index];
+                task.completed = match task.completed {
+                    Some(_) => None,
+                    None => Some(chrono::Local::now().naive_local()),
+                };
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    fn name(self) -> &'static str {
+        return match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        };
+    }
+
+    fn parse(text: &str) -> Option<Priority> {
+        return match text.trim().to_lowercase().as_str() {
+            "low" | "l" => Some(Priority::Low),
+            "normal" | "n" | "" => Some(Priority::Normal),
+            "high" | "h" => Some(Priority::High),
+            "urgent" | "u" => Some(Priority::Urgent),
+            _ => None,
+        };
+    }
+
+    fn raised(self) -> Priority {
+        return match self {
+            Priority::Low => Priority::Normal,
+            Priority::Normal => Priority::High,
+            _ => Priority::Urgent,
+        };
+    }
+
+    fn lowered(self) -> Priority {
+        return match self {
+            Priority::Urgent => Priority::High,
+            Priority::High => Priority::Normal,
+            _ => Priority::Low,
+        };
+    }
+}
+
+#[derive(Clone, PartialEq)]
 struct Task {
-    subject: Subject,
+    subject: u32, // a Subject::id
     description: String,
     date: chrono::NaiveDate,
+    time: Option<NaiveTime>, // none means any time that day
+    completed: Option<NaiveDateTime>,
+    priority: Priority,
+    occurrence: Option<(u32, NaiveDate)>, // the Series::id and date it was generated for, if it came from one
 }
 
 impl Task {
-    fn new(subject: Subject, description: &str, year: i32, month: u32, day: u32) -> Task {
-        return Task { 
-            subject,
-            description: description.to_string(),
-            date: NaiveDate::from_ymd_opt(year, month, day).expect(format!("{month}/{day}/{year} is not a valid date").as_str()),
-        };
+    // the moment a task stops being on time, the very end of its day when it has no time of its own.
+    // sorting by this puts same-day tasks in time order with the untimed ones last
+    fn due(&self) -> NaiveDateTime {
+        let end_of_day = NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).expect("a valid time");
+        return self.date.and_time(self.time.unwrap_or(end_of_day));
+    }
+
+    fn overdue(&self, now: NaiveDateTime) -> bool {
+        return self.completed.is_none() && now > self.due();
     }
 }
 
@@ -46,84 +164,334 @@ struct App {
     stdout: Stdout,
     screen_text: Vec<Vec<char>>,
     screen_color: Vec<Vec<Color>>,
+    // combining marks and the rest of an emoji sequence, by cell, with the character they go after.
+    // they only count while that character is still in the cell
+    screen_marks: HashMap<(usize, usize), (char, String)>,
+    drawn: Vec<Vec<(char, Color, Color)>>, // what the terminal shows now: text, color and background
+    drawn_marks: HashMap<(usize, usize), String>,
+    frame_cost: (usize, usize, Duration), // cells and bytes sent for the last frame, and the time it took
     running: bool,
     start: Instant,
     tab: usize,
     tasks: Vec<Task>,
+    series: Vec<Series>,
+    history: History,
     width: usize,
     height: usize,
-    today: NaiveDate
+    today: NaiveDate,
+    now: NaiveDateTime, // to the minute, refreshed by tick
+    path: PathBuf,
+    modified: Option<SystemTime>, // when the task file was last read or written here
+    file_error: Option<String>, // why the task file couldn't be read back in, while it can't
+    entry: entry::EntryForm,
+    show_completed: bool,
+    agenda_row: usize,
+    view: NaiveDate, // anchors which week and month those tabs show
+    cursor: NaiveDate,
+    cursor_task: usize,
+    confirm_delete: Option<usize>,
+    return_tab: usize,
+    subjects: Vec<Subject>,
+    subjects_path: PathBuf,
+    subjects_screen: Option<subjects::SubjectsScreen>,
+    search: Option<search::SearchScreen>,
+    config: config::Config,
 }
 
 
+// pulls out --file wherever it is and leaves everything else as the cli command
+fn parse_args() -> Result<(PathBuf, Vec<String>)> {
+    let mut path = storage::default_path();
+    let mut command = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--file" {
+            path = PathBuf::from(args.next().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "--file needs a path"))?);
+        } else if let Some(value) = arg.strip_prefix("--file=") {
+            path = PathBuf::from(value);
+        } else {
+            command.push(arg);
+        }
+    }
+    return Ok((path, command));
+}
+
 fn main() -> Result<()> {
-    let mut stdout = stdout();
-    let tasks = vec![
-        Task::new(Subject::Stats, "Homework 2", 2025, 9, 4),
-        Task::new(Subject::APUSH, "AMSCO MCQs + SAQ Pg. 22", 2025, 9, 4),
-    ];
-    let (width, height) = terminal::size()?;
+    let (path, command) = parse_args()?;
+    let config = config::load(&config::default_path())?;
+    let subjects_path = subjects::default_path();
+    let subjects = subjects::load(&subjects_path)?;
+    let (tasks, series, history) = storage::load(&path)?;
 
-    stdout.execute(terminal::EnterAlternateScreen)?;
-    terminal::enable_raw_mode()?;
-    stdout.execute(cursor::Hide)?;
-    stdout.execute(terminal::DisableLineWrap)?;
+    if !command.is_empty() {
+        if let Err(error) = cli::run(&command, &path, (tasks, series, history), &subjects) {
+            eprintln!("doras-todo: {error}");
+            process::exit(1);
+        }
+        return Ok(());
+    }
 
-    let mut app = App::new(stdout, tasks, width as usize, height as usize);
+    let (width, height) = terminal::size()?;
+    let guard = guard::enter()?;
+    let mut app = App::new(stdout(), (tasks, series, history), path, subjects, subjects_path, config, (width as usize, height as usize));
     app.run()?;
 
-    terminal::disable_raw_mode()?;
+    drop(guard);
     println!("bye bye");
     return Ok(());
 }
 
 impl App {
-    fn new(stdout: Stdout, tasks: Vec<Task>, width: usize, height: usize) -> App {
+    fn new(stdout: Stdout, (tasks, series, history): (Vec<Task>, Vec<Series>, History), path: PathBuf, subjects: Vec<Subject>, subjects_path: PathBuf, config: config::Config, (width, height): (usize, usize)) -> App {
+        let now = chrono::Local::now().naive_local();
+        let today = now.date();
         return App { 
             stdout,
             screen_text: vec![vec![' '; width]; height],
-            screen_color: vec![vec![Color::White; width]; height],
+            screen_color: vec![vec![config.theme.text; width]; height],
+            screen_marks: HashMap::new(),
+            drawn: Vec::new(),
+            drawn_marks: HashMap::new(),
+            frame_cost: (0, 0, Duration::ZERO),
             running: true,
             start: Instant::now(),
             tab: 0,
             tasks,
+            series,
+            history,
             width,
             height,
-            today: chrono::Local::now().date_naive()
+            today,
+            now,
+            modified: storage::modified(&path),
+            file_error: None,
+            path,
+            entry: entry::EntryForm::new(today, &subjects),
+            show_completed: true,
+            agenda_row: 0,
+            view: today,
+            cursor: today,
+            cursor_task: 0,
+            confirm_delete: None,
+            return_tab: 0,
+            subjects,
+            subjects_path,
+            subjects_screen: None,
+            search: None,
+            config,
         };
     }
 
     fn run(&mut self) -> Result<()> {
-        self.render_frame();
-        self.render_tabs();
+        self.render_current_tab();
 
         while self.running {
+            self.reload();
+            self.tick();
             self.draw()?;
             self.handle_input()?;
         }
         return Ok(());
     }
 
-    fn exit(&mut self) -> Result<()> {
+    // tasks go overdue and days roll over while the app sits open, so redraw when the minute changes
+    fn tick(&mut self) {
+        let now = chrono::Local::now().naive_local();
+        if now.date() == self.now.date() && now.hour() == self.now.hour() && now.minute() == self.now.minute() {
+            return;
+        }
+        self.now = now;
+        self.today = now.date();
+        self.render_current_tab();
+    }
+
+    // every change is saved as it's made, so there's nothing left to write here
+    fn exit(&mut self) {
         self.running = false;
-        self.stdout.execute(Clear(terminal::ClearType::All))?;
-        self.stdout.execute(terminal::LeaveAlternateScreen)?;
+    }
+
+    fn save(&mut self) -> Result<()> {
+        // the file on disk has a mistake in it, and writing over it would lose whatever else was
+        // changed there along with it
+        if self.file_error.is_some() {
+            return Ok(());
+        }
+        storage::save(&self.path, &self.tasks, &self.series, &self.history)?;
+        self.modified = storage::modified(&self.path);
+        return Ok(());
+    }
+
+    // the cli, or a text editor, can change the task file while the app is open. reading it back
+    // in before anything else happens keeps the next save from writing over those changes. a hand
+    // edit with a mistake in it leaves what's in the app alone until the file is fixed
+    fn reload(&mut self) {
+        let modified = storage::modified(&self.path);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        let old = self.tasks.clone();
+        match storage::load(&self.path) {
+            Ok(loaded) => {
+                (self.tasks, self.series, self.history) = loaded;
+                self.file_error = None;
+            },
+            Err(error) => {
+                self.file_error = Some(format!("{error}, not saving until it's fixed"));
+                self.render_current_tab();
+                return;
+            },
+        }
+
+        // an edit or delete in progress points at its task by position, which may have moved
+        let follow = |index: usize| self.tasks.iter().position(|task| *task == old[index]);
+        self.confirm_delete = self.confirm_delete.and_then(follow);
+        if let Some(editing) = self.entry.editing {
+            match follow(editing) {
+                Some(index) => self.entry.editing = Some(index),
+                None => self.finish_edit(),
+            }
+        }
+        if let Some(search) = &mut self.search {
+            search.update(&self.tasks, &self.subjects, self.today);
+        }
+        self.render_current_tab();
+    }
+
+    // every change to self.tasks goes through here so the file on disk never falls behind
+    fn apply(&mut self, change: Change) -> Result<()> {
+        return self.apply_together(vec![change]);
+    }
+
+    // the changes go into the history as one entry, so a single undo takes them all back
+    fn apply_together(&mut self, changes: Vec<Change>) -> Result<()> {
+        let mut steps = Vec::new();
+        for change in changes {
+            // an edit in progress refers to its task by index, so keep it pointing at the same one
+            let removed = change.removed(&self.tasks);
+            self.entry.editing = match self.entry.editing {
+                Some(editing) if removed.contains(&editing) => None,
+                Some(editing) => Some(editing - removed.iter().filter(|&&index| index < editing).count()),
+                None => None,
+            };
+            steps.push(history::Step::record(change, &mut self.tasks, &mut self.series));
+        }
+        self.history.push(steps);
+        return self.tasks_changed();
+    }
+
+    // u and ctrl-r. an edit in progress is dropped since its task may not be where it was
+    fn undo(&mut self, redo: bool) -> Result<()> {
+        if let Some(last) = self.history.last_date(redo) {
+            recurrence::expand(&self.series, &mut self.tasks, last);
+        }
+        let restored = match redo {
+            false => self.history.undo(&mut self.tasks, &mut self.series),
+            true => self.history.redo(&mut self.tasks, &mut self.series),
+        };
+        let Some(restored) = restored else { return Ok(()) };
+        if self.entry.editing.is_some() {
+            self.entry = entry::EntryForm::new(self.today, &self.subjects);
+        }
+        if let Some(&index) = restored.iter().find(|&&index| index < self.tasks.len() && self.visible(&self.tasks[index])) {
+            self.select_task(index);
+        }
+        return self.tasks_changed();
+    }
+
+    fn new_series(&self, task: Task, recurrence: recurrence::Recurrence) -> Change {
+        let series = Series {
+            id: recurrence::next_id(&self.series, &self.tasks),
+            subject: task.subject,
+            description: task.description,
+            time: task.time,
+            priority: task.priority,
+            start: task.date,
+            recurrence,
+            skipped: Vec::new(),
+        };
+        return Change::AddSeries(series);
+    }
+
+    fn tasks_changed(&mut self) -> Result<()> {
+        // rendering first lets a new series fill in its occurrences before they're saved
+        self.render_current_tab();
+        return self.save();
+    }
+
+    fn start_edit(&mut self, index: usize) {
+        let series = self.series_of(index).cloned();
+        self.entry.edit(index, &self.tasks[index], series.as_ref());
+        self.return_tab = self.tab;
+        self.tab = 3;
+        self.render_current_tab();
+    }
+
+    fn finish_edit(&mut self) {
+        self.entry = entry::EntryForm::new(self.today, &self.subjects);
+        self.tab = self.return_tab;
+        self.render_current_tab();
+    }
+
+    // the series a task was generated from, as long as that series is still going
+    fn series_of(&self, index: usize) -> Option<&Series> {
+        let (id, _) = self.tasks[index].occurrence?;
+        return self.series.iter().find(|series| series.id == id);
+    }
+
+    // whether a task should show up in the today, week and month tabs
+    fn visible(&self, task: &Task) -> bool {
+        let filtering = self.subjects.iter().any(|subject| subject.filtered);
+        let shown = !filtering || self.subjects.iter().any(|subject| subject.id == task.subject && subject.filtered);
+        return shown && (self.show_completed || task.completed.is_none());
+    }
+
+    // saves the subject filter and redraws, keeping the selection if its task is still shown
+    fn filter_changed(&mut self, selected: Option<usize>) -> Result<()> {
+        subjects::save(&self.subjects_path, &self.subjects)?;
+        if let Some(index) = selected && self.visible(&self.tasks[index]) {
+            self.select_task(index);
+        }
+        self.render_current_tab();
         return Ok(());
     }
 
+    // a task's subject, or a grey placeholder if it was removed from the subjects file
+    fn subject(&self, id: u32) -> Subject {
+        return self.subjects.iter().find(|subject| subject.id == id).cloned().unwrap_or(Subject::missing(id));
+    }
+
     fn clear_tab(&mut self) {
         for i in 1..(self.width - 2) {
             for j in 4..(self.height - 1) {
                 self.screen_text[j][i] = ' ';
-                self.screen_color[j][i] = Color::White;
+                self.screen_color[j][i] = self.config.theme.text;
             }
         }
     }
 
     fn switch_tab(&mut self, backward: bool) {
+        let selected = self.selected_task();
         self.tab += match backward { false => 1, true => 3 };
         self.tab %= 4;
+        if self.tab == 1 || self.tab == 2 {
+            self.cursor = self.clamp_cursor(self.cursor);
+            // the week and month tabs can sort a day differently, so follow the task rather than its position
+            if let Some(index) = selected && self.tasks[index].date == self.cursor {
+                self.select_task(index);
+            }
+        }
+        self.render_current_tab();
+    }
 
+    fn render_current_tab(&mut self) {
+        self.expand_series();
+        self.screen_marks.clear();
+        if self.width < MIN_WIDTH || self.height < MIN_HEIGHT {
+            self.render_too_small();
+            return;
+        }
+        self.render_frame();
         self.clear_tab();
         match self.tab {
             0 => self.render_today_tab(),
@@ -133,50 +501,308 @@ impl App {
             _ => {}, // this will never happen
         }
         self.render_tabs();
+        self.render_view_header();
+
+        if self.subjects_screen.is_some() {
+            self.render_subjects_screen();
+        }
+        if self.search.is_some() {
+            self.render_search_screen();
+        }
+        if let Some(index) = self.confirm_delete {
+            let description = self.tasks[index].description.clone();
+            let lines = match self.series_of(index) {
+                Some(series) => vec![
+                    format!("Skip \"{description}\" on {}?", self.tasks[index].date.format("%a %b %-d")),
+                    format!("It repeats {}.", series.recurrence.format()),
+                    String::new(),
+                    "y / enter to skip this one, a to stop the whole series,".to_string(),
+                    "anything else to keep it".to_string(),
+                ],
+                None => vec![
+                    format!("Delete \"{description}\"?"),
+                    String::new(),
+                    "y / enter to delete, anything else to keep it".to_string(),
+                ],
+            };
+            self.render_dialog(&lines);
+        }
     }
 
+    // a still background leaves nothing to redraw until a key comes in, apart from tick's once a minute
     fn handle_input(&mut self) -> Result<()> {
-        if self.running {
-            if poll(Duration::from_secs_f64(FRAMETIME - (self.start.elapsed().as_secs_f64() % FRAMETIME)))? {
-                match read()? {
-                    Key(key) => match key.code {
-                        KeyCode::Char('q') | KeyCode::Char('Q') => self.exit()?,
-                        KeyCode::Tab => self.switch_tab(false),
-                        KeyCode::BackTab => self.switch_tab(true),
-                        // KeyCode::Right => if self.cursor.0 < self.width { self.cursor.0 += 1 },
-                        // KeyCode::Left => if self.cursor.0 > 0 { self.cursor.0 -= 1 },
-                        // KeyCode::Down => if self.cursor.1 < self.height { self.cursor.1 += 1 },
-                        // KeyCode::Up => if self.cursor.1 > 0 { self.cursor.1 -= 1 },
-                        _ => {}
-                    },
-
-                    Resize(width, height) => self.resize(width as usize, height as usize),
-                    
-                    _ => {}
-                }
+        let timeout = if self.config.theme.animated(self.config.background) {
+            FRAMETIME - (self.start.elapsed().as_secs_f64() % FRAMETIME)
+        } else {
+            // up to the next minute, but looking in on the task file every second on the way
+            let now = chrono::Local::now().naive_local();
+            (60. - now.second() as f64 - now.nanosecond() as f64 / 1e9).min(1.)
+        };
+        if self.running && poll(Duration::from_secs_f64(timeout.max(0.)))? {
+            self.reload();
+            self.handle_event(read()?)?;
+            // drain anything else already queued (e.g. pasted text) before the next frame
+            while self.running && poll(Duration::ZERO)? {
+                self.handle_event(read()?)?;
             }
         }
         return Ok(());
     }
 
-    fn resize(&mut self, new_width: usize, new_height: usize) {
-        if new_height > self.height {
-            self.screen_text.resize(new_height, vec![' '; new_width]);
+    fn handle_event(&mut self, event: Event) -> Result<()> {
+        match event {
+            Key(key) if key.kind == KeyEventKind::Release => {},
+            Key(key) if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) => self.exit(),
+            Key(key) if self.confirm_delete.is_some() => {
+                let index = self.confirm_delete.take().expect("checked by the match guard");
+                match (key.code, self.series_of(index).map(|series| series.id)) {
+                    (KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter, _) => self.apply(Change::Delete(index))?,
+                    (KeyCode::Char('a') | KeyCode::Char('A'), Some(id)) => self.apply(Change::EndSeries(id))?,
+                    _ => self.render_current_tab(),
+                }
+            },
+            Key(key) if self.search.is_some() => {
+                let mut search = self.search.take().expect("checked by the match guard");
+                match search.handle_key(key) {
+                    search::Outcome::Open => {
+                        search.update(&self.tasks, &self.subjects, self.today);
+                        self.search = Some(search);
+                    },
+                    search::Outcome::Close => {},
+                    search::Outcome::Choose(index) => {
+                        let before = self.subjects.clone();
+                        self.jump_to_task(index);
+                        if self.subjects != before {
+                            subjects::save(&self.subjects_path, &self.subjects)?;
+                        }
+                    },
+                }
+                self.render_current_tab();
+            },
+            Key(key) if self.subjects_screen.is_some() => {
+                let mut screen = self.subjects_screen.take().expect("checked by the match guard");
+                let before = self.subjects.clone();
+                if screen.handle_key(key, &mut self.subjects) {
+                    self.subjects_screen = Some(screen);
+                }
+                if self.subjects != before {
+                    subjects::save(&self.subjects_path, &self.subjects)?;
+                }
+                self.render_current_tab();
+            },
+            Key(key) if self.tab == 3 && !matches!(key.code, KeyCode::Tab | KeyCode::BackTab | KeyCode::Esc) => {
+                let editing = self.entry.editing;
+                match (self.entry.handle_key(key, &self.subjects, self.today), editing) {
+                    (Some((mut task, None)), Some(index)) => {
+                        task.completed = self.tasks[index].completed;
+                        task.occurrence = self.tasks[index].occurrence;
+                        self.apply(Change::Edit(index, task))?;
+                        self.finish_edit();
+                    },
+                    // giving a one-off task a repeat turns it into the first occurrence of a new series
+                    (Some((task, Some(recurrence))), Some(index)) => {
+                        let series = self.new_series(task, recurrence);
+                        self.apply_together(vec![Change::Delete(index), series])?;
+                        self.finish_edit();
+                    },
+                    (Some((task, None)), None) => self.apply(Change::Add(task))?,
+                    (Some((task, Some(recurrence))), None) => self.apply(self.new_series(task, recurrence))?,
+                    (None, _) => self.render_current_tab(),
+                }
+            },
+            Key(key) => match key.code {
+                KeyCode::Char('q') | KeyCode::Char('Q') => self.exit(),
+                KeyCode::Esc if self.tab == 3 && self.entry.editing.is_some() => self.finish_edit(),
+                KeyCode::Esc if self.tab == 3 => { self.tab = 0; self.render_current_tab(); },
+                KeyCode::Tab => self.switch_tab(false),
+                KeyCode::BackTab => self.switch_tab(true),
+                KeyCode::Char('c') => {
+                    self.show_completed = !self.show_completed;
+                    self.render_current_tab();
+                },
+                KeyCode::Char('x') | KeyCode::Char(' ') => {
+                    if let Some(index) = self.selected_task() {
+                        self.apply(Change::ToggleCompleted(index))?;
+                    }
+                },
+                KeyCode::Char('s') => {
+                    self.subjects_screen = Some(subjects::SubjectsScreen::new());
+                    self.render_current_tab();
+                },
+                KeyCode::Char('/') => {
+                    let mut search = search::SearchScreen::new();
+                    search.update(&self.tasks, &self.subjects, self.today);
+                    self.search = Some(search);
+                    self.render_current_tab();
+                },
+                KeyCode::Char('e') => {
+                    if let Some(index) = self.selected_task() {
+                        self.start_edit(index);
+                    }
+                },
+                KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Char('-') => {
+                    if let Some(index) = self.selected_task() {
+                        let mut task = self.tasks[index].clone();
+                        task.priority = if key.code == KeyCode::Char('-') { task.priority.lowered() } else { task.priority.raised() };
+                        self.apply(Change::Edit(index, task))?;
+                        // sorting by priority can move it, so keep the cursor on it
+                        self.select_task(index);
+                        self.render_current_tab();
+                    }
+                },
+                KeyCode::Char('T') => self.next_theme(),
+                KeyCode::Char('u') if self.tab < 3 => self.undo(false)?,
+                KeyCode::Char('r') if self.tab < 3 && key.modifiers.contains(KeyModifiers::CONTROL) => self.undo(true)?,
+                KeyCode::Char('f') if self.tab < 3 => {
+                    if let Some(index) = self.selected_task() {
+                        let id = self.tasks[index].subject;
+                        if let Some(subject) = self.subjects.iter_mut().find(|subject| subject.id == id) {
+                            subject.filtered = !subject.filtered;
+                        }
+                        self.filter_changed(Some(index))?;
+                    }
+                },
+                KeyCode::Char('F') if self.tab < 3 => {
+                    let selected = self.selected_task();
+                    for subject in &mut self.subjects {
+                        subject.filtered = false;
+                    }
+                    self.filter_changed(selected)?;
+                },
+                KeyCode::Char('o') if self.tab < 3 => {
+                    let selected = self.selected_task();
+                    self.config.cycle_sort(self.tab);
+                    if let Some(index) = selected {
+                        self.select_task(index);
+                    }
+                    self.render_current_tab();
+                },
+                KeyCode::Char('d') | KeyCode::Delete => {
+                    if let Some(index) = self.selected_task() {
+                        self.confirm_delete = Some(index);
+                        self.render_current_tab();
+                    }
+                },
+                _ => { self.handle_navigation(key); }
+            },
+
+            Resize(width, height) => self.resize(width as usize, height as usize),
+            
+            _ => {}
         }
-        if new_width > self.width {
-            for row in &mut self.screen_text {
-                row.resize(new_width, ' ');
+        return Ok(());
+    }
+
+    // steps to the next theme without touching the config file, passing over files that don't load
+    fn next_theme(&mut self) {
+        let names = theme::available();
+        let current = names.iter().position(|name| *name == self.config.theme.name).unwrap_or(0);
+        for offset in 1..names.len() {
+            if let Ok(theme) = theme::load(&names[(current + offset) % names.len()]) {
+                self.config.theme = theme;
+                break;
             }
         }
+        // the frame is only ever colored by this, so it has to be redone in the new text color
+        self.screen_color = vec![vec![self.config.theme.text; self.width]; self.height];
+        self.render_current_tab();
+    }
 
-        self.render_frame();
+    // everything is drawn relative to the size, so start over from blank buffers
+    fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.screen_text = vec![vec![' '; width]; height];
+        self.screen_color = vec![vec![self.config.theme.text; width]; height];
+        self.render_current_tab();
+    }
+
+    fn render_too_small(&mut self) {
+        for row in &mut self.screen_text {
+            row.fill(' ');
+        }
+        for row in &mut self.screen_color {
+            row.fill(self.config.theme.text);
+        }
+        let lines = [
+            "terminal too small".to_string(),
+            format!("{}x{}, needs {MIN_WIDTH}x{MIN_HEIGHT}", self.width, self.height),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            let y = (self.height / 2 + i).saturating_sub(1);
+            if y >= self.height {
+                break;
+            }
+            let text: String = line.chars().take(self.width).collect();
+            let x = (self.width - text.chars().count()) / 2;
+            self.render_string(&text, x, y);
+            if i > 0 && !text.is_empty() {
+                self.dim_area(x, y, x + text.chars().count() - 1, y);
+            }
+        }
     }
 
+    // one grapheme per cell, or two for wide ones, cut off at the edge of the screen
     fn render_string(&mut self, string: &str, x: usize, y: usize) {
-        let mut chars = string.chars();
-        for i in 0..chars.clone().count() {
-            self.screen_text[y][x + i] = chars.next().expect("what that wasnt supposed to happen");
+        if y >= self.height {
+            return;
+        }
+        let mut x = x;
+        for grapheme in string.graphemes(true) {
+            let width = text::grapheme_width(grapheme);
+            if width == 0 {
+                continue;
+            }
+            if x + width > self.width {
+                break;
+            }
+            let mut chars = grapheme.chars();
+            let base = chars.next().expect("graphemes aren't empty");
+            self.screen_text[y][x] = base;
+            match chars.as_str() {
+                "" => self.screen_marks.remove(&(x, y)),
+                marks => self.screen_marks.insert((x, y), (base, marks.to_string())),
+            };
+            if width == 2 {
+                self.screen_text[y][x + 1] = text::WIDE_TAIL;
+            }
+            x += width;
+        }
+    }
+
+    // like render_string, but cut short with … to end before `right`. returns how many cells it took
+    fn render_clipped(&mut self, string: &str, x: usize, y: usize, right: usize) -> usize {
+        let string = text::truncate(string, right.saturating_sub(x));
+        self.render_string(&string, x, y);
+        return text::width(&string);
+    }
+
+    fn marks_at(&self, x: usize, y: usize) -> &str {
+        return match self.screen_marks.get(&(x, y)) {
+            Some((base, marks)) if *base == self.screen_text[y][x] => marks,
+            _ => "",
+        };
+    }
+
+    // what actually goes in each cell of a row. a wide grapheme needs its tail right after it, and
+    // a tail needs its grapheme right before it, so whichever half is left when something is drawn
+    // over the other becomes a space rather than confusing the terminal about where it is
+    fn shown_row(&self, y: usize) -> Vec<(char, String)> {
+        let mut row: Vec<(char, String)> = (0..self.width).map(|x| (self.screen_text[y][x], self.marks_at(x, y).to_string())).collect();
+        let mut x = 0;
+        while x < self.width {
+            let wide = row[x].0 != text::WIDE_TAIL && text::cell_width(row[x].0, &row[x].1) == 2;
+            let tail = x + 1 < self.width && row[x + 1].0 == text::WIDE_TAIL;
+            if wide && tail {
+                x += 2;
+                continue;
+            }
+            if wide || row[x].0 == text::WIDE_TAIL {
+                row[x] = (' ', String::new());
+            }
+            x += 1;
         }
+        return row;
     }
 
     fn color_area(&mut self, color: Color, x_min: usize, y_min: usize, x_max: usize, y_max: usize) {
@@ -188,7 +814,7 @@ impl App {
     }
 
     fn dim_area(&mut self, x_min: usize, y_min: usize, x_max: usize, y_max: usize) {
-        self.color_area(Color::DarkGrey, x_min, y_min, x_max, y_max);
+        self.color_area(self.config.theme.dim, x_min, y_min, x_max, y_max);
     }
 
     fn render_frame(&mut self) {
@@ -216,8 +842,8 @@ impl App {
 
         for i in 1..(self.width - 2) {
             self.screen_text[3][i] = '─';
-            self.screen_text[self.height - 1][i] = '─';
         }
+        self.render_bottom_border();
 
         for i in 4..(self.height - 1) {
             self.screen_text[i][0] = '│';
@@ -234,6 +860,33 @@ impl App {
             self.render_string("█ █ █▀█ █▄▀ ▄▀█ ▀ ▀▄     █  █▀█ █ █ █▀█", self.width - 40, 1);
             self.render_string("█▄▀ █▄█ █   ▀▄█   ▄▄▀    █  █▄█ █▄▀ █▄█", self.width - 40, 2);
         }
+
+        // the subject filter goes in the gap between the tabs and the logo, as many as fit
+        let end = if self.width > 90 { self.width - 42 } else { self.width - 3 };
+        if end <= 53 {
+            return;
+        }
+        self.render_string(&" ".repeat(end - 52), 52, 1);
+        self.color_area(self.config.theme.text, 52, 1, end, 1);
+        let filtered: Vec<Subject> = self.subjects.iter().filter(|subject| subject.filtered).cloned().collect();
+        if filtered.is_empty() {
+            return;
+        }
+        self.render_string("only", 53, 1);
+        self.dim_area(53, 1, 56, 1);
+        let mut x = 58;
+        for subject in filtered {
+            let code = text::width(&subject.code);
+            if x + 3 + code > end {
+                if x <= end {
+                    self.render_string("…", x, 1);
+                }
+                break;
+            }
+            self.render_string(&format!("██ {}", subject.code), x, 1);
+            self.color_area(self.subject_color(subject.id), x, 1, x + 1, 1);
+            x += 5 + code;
+        }
     }
 
     fn render_tabs(&mut self) {
@@ -251,7 +904,7 @@ impl App {
         self.dim_area(0, 0, 50, 2);
         match self.tab {
             0 => {
-                self.color_area(Color::White, 0, 0, 12, 2);
+                self.color_area(self.config.theme.text, 0, 0, 12, 2);
                 self.screen_text[0][12] = '╮';
                 for i in 1..12 {
                     self.screen_text[3][i] = ' ';
@@ -260,7 +913,7 @@ impl App {
                 self.screen_text[3][12] = '╰';
             },
             1 => { 
-                self.color_area(Color::White, 12, 0, 24, 2);
+                self.color_area(self.config.theme.text, 12, 0, 24, 2);
                 self.screen_text[0][12] = '╭';
                 self.screen_text[0][24] = '╮';
                 self.screen_text[3][12] = '╯';
@@ -270,7 +923,7 @@ impl App {
                 }
             },
             2 => { 
-                self.color_area(Color::White, 24, 0, 37, 2);
+                self.color_area(self.config.theme.text, 24, 0, 37, 2);
                 self.screen_text[0][24] = '╭';
                 self.screen_text[0][37] = '╮';
                 self.screen_text[3][24] = '╯';
@@ -280,7 +933,7 @@ impl App {
                 }
             },
             3 => { 
-                self.color_area(Color::White, 37, 0, 50, 2);
+                self.color_area(self.config.theme.text, 37, 0, 50, 2);
                 self.screen_text[0][37] = '╭';
                 self.screen_text[3][37] = '╯';
                 self.screen_text[3][50] = '╰';
@@ -292,32 +945,108 @@ impl App {
         }
     }
 
+    // the line under the tabs, with a task file that couldn't be read back in on its left
+    fn render_bottom_border(&mut self) {
+        let y = self.height - 1;
+        for i in 1..(self.width - 2) {
+            self.screen_text[y][i] = '─';
+            self.screen_color[y][i] = self.config.theme.text;
+        }
+        if let Some(error) = &self.file_error {
+            let error = text::truncate(&format!(" {error} "), self.width - 6);
+            self.render_string(&error, 2, y);
+            self.color_area(self.config.theme.alert, 2, y, 1 + text::width(&error), y);
+        }
+    }
+
+    // only cells that differ from what's already on the terminal are sent, a run of them sharing
+    // colors in one print, and the whole frame goes out in one write
     fn draw(&mut self) -> Result<()> {
-        self.stdout.execute(BeginSynchronizedUpdate)?;
-        self.stdout.queue(Clear(terminal::ClearType::Purge))?;
-        self.stdout.queue(cursor::MoveTo(0, 0))?;
+        let started = Instant::now();
+        let mut out: Vec<u8> = Vec::new();
+        if self.drawn.len() != self.height || self.drawn.first().is_some_and(|row| row.len() != self.width) {
+            // a noncharacter never matches a real cell, so everything gets drawn
+            self.drawn = vec![vec![('\u{ffff}', Color::Reset, Color::Reset); self.width]; self.height];
+            self.drawn_marks.clear();
+            out.queue(Clear(terminal::ClearType::All))?;
+        }
+        if self.config.stats && self.width >= MIN_WIDTH && self.height >= MIN_HEIGHT {
+            let (cells, bytes, time) = self.frame_cost;
+            let stats = format!(" {cells} cells · {:.1} kB · {:.1} ms ", bytes as f64 / 1000., time.as_secs_f64() * 1000.);
+            let x = self.width - 3 - text::width(&stats);
+            // the border under it is only redrawn with the tab, so a shorter line than the last
+            // frame's would leave the old one's start showing
+            self.render_bottom_border();
+            self.render_string(&stats, x, self.height - 1);
+            self.dim_area(x, self.height - 1, self.width - 4, self.height - 1);
+        }
 
+        let time = self.start.elapsed().as_secs_f64();
+        let (background, size) = (self.config.background, (self.width, self.height));
+        let mut cells = 0;
         for y in 0..self.height {
-            for x in 0..self.width {
-                let intensity = (
-	    			(8. * 
-                        f64::sin(
-	    				x as f64 * 0.1 
-	    				- y as f64 * 0.15 
-	    				+ self.start.elapsed().as_secs_f64() * 3.)
-                    ) as i8
-                    + 30
-                ) as u8;
-
-                self.stdout.queue(style::PrintStyledContent(
-                    self.screen_text[y][x]
-                    .with(self.screen_color[y][x])
-                    .on(style::Color::Rgb{r: intensity, g: 0, b: intensity})
-                ))?;
+            let row = self.shown_row(y);
+            // a wide grapheme and its tail are drawn and compared as one
+            let span = |x: usize| if x + 1 < row.len() && row[x + 1].0 == text::WIDE_TAIL { 2 } else { 1 };
+            let mut x = 0;
+            while x < self.width {
+                let color = palette::quantize(self.screen_color[y][x], self.config.colors);
+                let color_behind = palette::quantize(self.config.theme.background_color(background, (x, y), size, time), self.config.colors);
+                let start = x;
+                let mut run = String::new();
+                while x < self.width {
+                    let (text, marks) = &row[x];
+                    let cell = (
+                        self.config.theme.borders.glyph(*text),
+                        palette::quantize(self.screen_color[y][x], self.config.colors),
+                        palette::quantize(self.config.theme.background_color(background, (x, y), size, time), self.config.colors),
+                    );
+                    let unchanged = cell == self.drawn[y][x]
+                        && self.drawn_marks.get(&(x, y)).map_or("", |drawn| drawn.as_str()) == marks
+                        && (span(x) == 1 || self.drawn[y][x + 1] == (text::WIDE_TAIL, cell.1, cell.2));
+                    if unchanged || cell.1 != color || cell.2 != color_behind {
+                        break;
+                    }
+                    run.push(cell.0);
+                    run.push_str(marks);
+                    self.drawn[y][x] = cell;
+                    match marks.as_str() {
+                        "" => self.drawn_marks.remove(&(x, y)),
+                        marks => self.drawn_marks.insert((x, y), marks.to_string()),
+                    };
+                    if span(x) == 2 {
+                        self.drawn[y][x + 1] = (text::WIDE_TAIL, cell.1, cell.2);
+                    }
+                    x += span(x);
+                    // terminals don't all agree how wide a sequence like 👩‍💻 is, so in case this one
+                    // spilled over, the next two cells are drawn again starting from a known position
+                    if !marks.is_empty() {
+                        for after in x..(x + 2).min(self.width) {
+                            self.drawn[y][after].0 = '\u{ffff}';
+                        }
+                        break;
+                    }
+                }
+                if run.is_empty() {
+                    x += span(x);
+                    continue;
+                }
+                cells += x - start;
+                out.queue(cursor::MoveTo(start as u16, y as u16))?;
+                match self.config.colors {
+                    palette::ColorSupport::Ansi16 => write!(out, "\x1b[{};{}m{run}", palette::sgr_16(color, false), palette::sgr_16(color_behind, true))?,
+                    _ => { out.queue(style::PrintStyledContent(run.with(color).on(color_behind)))?; },
+                }
             }
-            self.stdout.execute(cursor::MoveToNextLine(1))?;
         }
-        self.stdout.execute(EndSynchronizedUpdate)?;
+
+        if !out.is_empty() {
+            self.stdout.queue(BeginSynchronizedUpdate)?;
+            self.stdout.write_all(&out)?;
+            self.stdout.queue(EndSynchronizedUpdate)?;
+            self.stdout.flush()?;
+        }
+        self.frame_cost = (cells, out.len(), started.elapsed());
         return Ok(());
     }
 }
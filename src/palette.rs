@@ -0,0 +1,110 @@
+use crossterm::style::Color;
+use std::env;
+
+// everything is drawn in full rgb and brought down to what the terminal can show on the way out,
+// so themes and subject colors never have to think about it
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorSupport {
+    // "auto" is None, meaning go by detect
+    pub fn parse(text: &str) -> Option<Option<ColorSupport>> {
+        return match text.trim().to_lowercase().as_str() {
+            "auto" => Some(None),
+            "truecolor" | "24bit" | "rgb" => Some(Some(ColorSupport::TrueColor)),
+            "256" => Some(Some(ColorSupport::Ansi256)),
+            "16" => Some(Some(ColorSupport::Ansi16)),
+            _ => None,
+        };
+    }
+}
+
+// COLORTERM is how terminals announce truecolor, TERM says 256 colors by name, and anything
+// else (the linux console, a bare "xterm", screen) is safest assumed to have the basic 16
+pub fn detect() -> ColorSupport {
+    let colorterm = env::var("COLORTERM").unwrap_or_default().to_lowercase();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorSupport::TrueColor;
+    }
+    let term = env::var("TERM").unwrap_or_default().to_lowercase();
+    if term.contains("truecolor") || term.contains("direct") {
+        return ColorSupport::TrueColor;
+    }
+    if term.contains("256color") {
+        return ColorSupport::Ansi256;
+    }
+    return ColorSupport::Ansi16;
+}
+
+// roughly what terminals show for the 16 standard colors by default
+pub const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn distance((r, g, b): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i32 {
+    let (dr, dg, db) = (r as i32 - r2 as i32, g as i32 - g2 as i32, b as i32 - b2 as i32);
+    return dr * dr + dg * dg + db * db;
+}
+
+pub fn nearest_ansi(rgb: (u8, u8, u8)) -> Color {
+    return ANSI.iter().min_by_key(|(_, ansi)| distance(rgb, *ansi)).map(|(color, _)| *color).expect("the table isn't empty");
+}
+
+// the 256 color palette is the 16 above, a 6x6x6 color cube and a 24 step grey ramp.
+// whichever of the nearest cube color and the nearest grey is closer wins
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn nearest_256(rgb: (u8, u8, u8)) -> Color {
+    let level = |channel: u8| (0..6).min_by_key(|&i| (CUBE[i] as i32 - channel as i32).abs()).expect("the cube has levels");
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = (CUBE[r], CUBE[g], CUBE[b]);
+
+    let average = (rgb.0 as i32 + rgb.1 as i32 + rgb.2 as i32) / 3;
+    let step = ((average - 8).max(0) / 10).min(23);
+    let grey = (8 + step * 10) as u8;
+
+    if distance(rgb, (grey, grey, grey)) < distance(rgb, cube) {
+        return Color::AnsiValue(232 + step as u8);
+    }
+    return Color::AnsiValue(16 + 36 * r as u8 + 6 * g as u8 + b as u8);
+}
+
+// crossterm writes even the 16 named colors as 256 color codes, which the consoles that need them
+// most don't understand, so in 16 color mode the codes are written out by hand
+pub fn sgr_16(color: Color, background: bool) -> u8 {
+    let offset = if background { 10 } else { 0 };
+    return match ANSI.iter().position(|(named, _)| *named == color) {
+        Some(i) if i < 8 => 30 + i as u8 + offset,
+        Some(i) => 90 + (i - 8) as u8 + offset,
+        None => 39 + offset, // the terminal's default
+    };
+}
+
+pub fn quantize(color: Color, support: ColorSupport) -> Color {
+    let Color::Rgb{ r, g, b } = color else { return color };
+    return match support {
+        ColorSupport::TrueColor => color,
+        ColorSupport::Ansi256 => nearest_256((r, g, b)),
+        ColorSupport::Ansi16 => nearest_ansi((r, g, b)),
+    };
+}
@@ -0,0 +1,304 @@
+use chrono::{Datelike, Days, NaiveDate, NaiveTime, Weekday};
+use std::collections::HashSet;
+use crate::{Priority, Task, dates};
+
+// a series is a template for tasks that come back on a schedule. its occurrences are only turned
+// into real tasks (linked back through Task::occurrence) once a view needs them, and from then on
+// each one is a normal task that can be finished, edited or deleted without touching the others.
+// deleting one records its date in `skipped` so it isn't generated again. only occurrences up to
+// the horizon are saved, along with any later ones that were finished or edited, since the rest
+// can always be generated again exactly as they were
+
+const HINT: &str = "try \"daily\", \"weekly on mon wed\", \"every 3 days\" or \"monthly on 15\", \
+                    optionally with \"until 12/19\" or \"10 times\"";
+
+#[derive(Clone, PartialEq)]
+pub enum Rule {
+    Daily,
+    Weekly(Vec<Weekday>), // sorted monday first, never empty
+    EveryDays(u32),
+    Monthly(u32), // day of the month, months that are too short use their last day
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum End {
+    Never,
+    Until(NaiveDate),
+    Count(u32),
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Recurrence {
+    pub rule: Rule,
+    pub end: End,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Series {
+    pub id: u32,
+    pub subject: u32, // a Subject::id
+    pub description: String,
+    pub time: Option<NaiveTime>,
+    pub priority: Priority,
+    pub start: NaiveDate,
+    pub recurrence: Recurrence,
+    pub skipped: Vec<NaiveDate>,
+}
+
+fn parse_day_of_month(text: &str) -> Option<u32> {
+    let digits = text.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    return digits.parse::<u32>().ok().filter(|day| (1..=31).contains(day));
+}
+
+// `start` fills in "weekly" and "monthly" on their own, `today` is what "until fri" is relative to
+pub fn parse(text: &str, start: NaiveDate, today: NaiveDate) -> Result<Recurrence, String> {
+    let text = text.trim().to_lowercase().replace(',', " ");
+    let mut words: Vec<&str> = text.split_whitespace().filter(|word| *word != "and").collect();
+
+    let mut end = End::Never;
+    if let Some(i) = words.iter().position(|word| *word == "until") {
+        let until = dates::parse_date(&words[i + 1..].join(" "), today)?;
+        if until < start {
+            return Err(format!("it would stop on {} before it starts", until.format("%a %b %-d")));
+        }
+        end = End::Until(until);
+        words.truncate(i);
+    } else if let [.., count, "times" | "time"] = words.as_slice() {
+        let count = count.parse::<u32>().ok().filter(|&count| count > 0)
+            .ok_or(format!("\"{count}\" isn't a number of times"))?;
+        end = End::Count(count);
+        words.truncate(words.len() - 2);
+        if words.last() == Some(&"for") {
+            words.pop();
+        }
+    }
+
+    let rule = match words.as_slice() {
+        [] => return Err(format!("say how often it repeats, {HINT}")),
+        ["daily"] | ["every" | "each", "day"] => Rule::Daily,
+        ["every", "other", "day"] => Rule::EveryDays(2),
+        ["every", count, "days" | "day"] => match count.parse::<u32>() {
+            Ok(1) => Rule::Daily,
+            Ok(count) if count > 1 => Rule::EveryDays(count),
+            _ => return Err(format!("\"{count}\" isn't a number of days")),
+        },
+        ["weekly"] | ["every", "week"] => Rule::Weekly(vec![start.weekday()]),
+        ["weekdays"] | ["every", "weekday"] => Rule::Weekly(vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]),
+        ["weekly", "on", days @ ..] | ["every" | "each", days @ ..] if !days.is_empty() && days.iter().all(|day| dates::parse_weekday(day).is_some()) => {
+            let mut weekdays: Vec<Weekday> = days.iter().filter_map(|day| dates::parse_weekday(day)).collect();
+            weekdays.sort_by_key(|weekday| weekday.num_days_from_monday());
+            weekdays.dedup();
+            Rule::Weekly(weekdays)
+        },
+        ["monthly"] | ["every", "month"] => Rule::Monthly(start.day()),
+        ["monthly", "on", day] | ["monthly", "on", "the", day] => match parse_day_of_month(day) {
+            Some(day) => Rule::Monthly(day),
+            None => return Err(format!("\"{day}\" isn't a day of the month")),
+        },
+        _ => return Err(format!("\"{text}\" isn't a repeat I understand, {HINT}")),
+    };
+    return Ok(Recurrence { rule, end });
+}
+
+impl Recurrence {
+    // the same wording parse reads back, which is also how it's written to the task file
+    pub fn format(&self) -> String {
+        let rule = match &self.rule {
+            Rule::Daily => "daily".to_string(),
+            Rule::EveryDays(count) => format!("every {count} days"),
+            Rule::Weekly(weekdays) => {
+                let days: Vec<String> = weekdays.iter().map(|weekday| weekday.to_string().to_lowercase()).collect();
+                format!("weekly on {}", days.join(","))
+            },
+            Rule::Monthly(day) => format!("monthly on {day}"),
+        };
+        return match self.end {
+            End::Never => rule,
+            End::Until(date) => format!("{rule} until {}", date.format("%Y-%m-%d")),
+            End::Count(count) => format!("{rule} {count} times"),
+        };
+    }
+
+    fn matches(&self, start: NaiveDate, date: NaiveDate) -> bool {
+        return match &self.rule {
+            Rule::Daily => true,
+            Rule::EveryDays(count) => (date - start).num_days() % *count as i64 == 0,
+            Rule::Weekly(weekdays) => weekdays.contains(&date.weekday()),
+            Rule::Monthly(day) => date.day() == (*day).min(date.num_days_in_month() as u32),
+        };
+    }
+
+    // every date the series lands on from `start` through `last`, skipped ones included
+    pub fn dates(&self, start: NaiveDate, last: NaiveDate) -> Vec<NaiveDate> {
+        let last = match self.end {
+            End::Until(until) => last.min(until),
+            _ => last,
+        };
+        let mut dates = Vec::new();
+        for date in start.iter_days().take_while(|date| *date <= last) {
+            if let End::Count(count) = self.end && dates.len() >= count as usize {
+                break;
+            }
+            if self.matches(start, date) {
+                dates.push(date);
+            }
+        }
+        return dates;
+    }
+}
+
+impl Series {
+    pub fn occurrence(&self, date: NaiveDate) -> Task {
+        return Task {
+            subject: self.subject,
+            description: self.description.clone(),
+            date,
+            time: self.time,
+            completed: None,
+            priority: self.priority,
+            occurrence: Some((self.id, date)),
+        };
+    }
+}
+
+// ids are never handed out twice, even after a series is stopped, since its finished tasks still point at it
+pub fn next_id(series: &[Series], tasks: &[Task]) -> u32 {
+    let in_series = series.iter().map(|series| series.id);
+    let in_tasks = tasks.iter().filter_map(|task| task.occurrence.map(|(id, _)| id));
+    return in_series.chain(in_tasks).max().unwrap_or(0) + 1;
+}
+
+// the next few dates a recurrence would land on, for previewing it before it's saved
+pub fn upcoming(recurrence: &Recurrence, start: NaiveDate, count: usize) -> Vec<NaiveDate> {
+    let horizon = start.checked_add_days(Days::new(400)).unwrap_or(NaiveDate::MAX);
+    return recurrence.dates(start, horizon).into_iter().take(count).collect();
+}
+
+// how far past today occurrences are kept, which is also as far ahead as the today tab looks
+pub const HORIZON_DAYS: u64 = 7;
+
+pub fn horizon(today: NaiveDate) -> NaiveDate {
+    return today.checked_add_days(Days::new(HORIZON_DAYS)).unwrap_or(today);
+}
+
+// whether a task is exactly what its series would generate, so leaving it out of the file loses nothing
+pub fn untouched(task: &Task, series: &[Series]) -> bool {
+    let Some((id, date)) = task.occurrence else { return false };
+    return series.iter().any(|series| series.id == id && series.occurrence(date) == *task);
+}
+
+// makes sure every occurrence through `last` exists as a task, returning whether any were added
+pub fn expand(series: &[Series], tasks: &mut Vec<Task>, last: NaiveDate) -> bool {
+    let existing: HashSet<(u32, NaiveDate)> = tasks.iter().filter_map(|task| task.occurrence).collect();
+    let before = tasks.len();
+    for series in series {
+        for date in series.recurrence.dates(series.start, last) {
+            if !existing.contains(&(series.id, date)) && !series.skipped.contains(&date) {
+                tasks.push(series.occurrence(date));
+            }
+        }
+    }
+    return tasks.len() != before;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    }
+
+    // a wednesday
+    fn start() -> NaiveDate {
+        return date(2025, 9, 3);
+    }
+
+    fn series(recurrence: Recurrence, skipped: Vec<NaiveDate>) -> Series {
+        return Series {
+            id: 1,
+            subject: 1,
+            description: "Homework".to_string(),
+            time: None,
+            priority: Priority::Normal,
+            start: start(),
+            recurrence,
+            skipped,
+        };
+    }
+
+    #[test]
+    fn format_reads_back() {
+        for text in [
+            "daily",
+            "every 3 days",
+            "weekly on mon,wed,fri",
+            "monthly on 31",
+            "daily until 2025-12-19",
+            "weekly on tue 10 times",
+        ] {
+            let recurrence = parse(text, start(), start()).unwrap();
+            assert_eq!(recurrence.format(), text);
+            assert!(parse(&recurrence.format(), start(), start()).unwrap() == recurrence);
+        }
+        // anything else comes back out in the same wording the file uses
+        assert_eq!(parse("every wed, fri", start(), start()).unwrap().format(), "weekly on wed,fri");
+        assert_eq!(parse("monthly", start(), start()).unwrap().format(), "monthly on 3");
+    }
+
+    #[test]
+    fn monthly_on_a_late_day() {
+        let recurrence = parse("monthly on 31", start(), start()).unwrap();
+        assert_eq!(
+            recurrence.dates(date(2025, 1, 31), date(2025, 5, 31)),
+            vec![date(2025, 1, 31), date(2025, 2, 28), date(2025, 3, 31), date(2025, 4, 30), date(2025, 5, 31)],
+        );
+        assert_eq!(recurrence.dates(date(2024, 2, 1), date(2024, 2, 29)), vec![date(2024, 2, 29)]);
+    }
+
+    #[test]
+    fn counts_include_skipped_dates() {
+        let recurrence = parse("daily 5 times", start(), start()).unwrap();
+        assert_eq!(recurrence.dates(start(), date(2025, 12, 31)).len(), 5);
+
+        // skipping one doesn't push the series out to a sixth
+        let series = series(recurrence, vec![date(2025, 9, 4)]);
+        let mut tasks = Vec::new();
+        assert!(expand(&[series], &mut tasks, date(2025, 12, 31)));
+        let dates: Vec<NaiveDate> = tasks.iter().map(|task| task.date).collect();
+        assert_eq!(dates, vec![date(2025, 9, 3), date(2025, 9, 5), date(2025, 9, 6), date(2025, 9, 7)]);
+    }
+
+    #[test]
+    fn expanding_again_adds_nothing() {
+        let series = series(parse("weekly on mon,wed", start(), start()).unwrap(), Vec::new());
+        let mut tasks = Vec::new();
+        assert!(expand(std::slice::from_ref(&series), &mut tasks, date(2025, 9, 30)));
+        assert_eq!(tasks.len(), 8);
+        assert!(!expand(std::slice::from_ref(&series), &mut tasks, date(2025, 9, 30)));
+        assert!(untouched(&tasks[0], std::slice::from_ref(&series)));
+        tasks[0].completed = Some(start().and_hms_opt(12, 0, 0).unwrap());
+        assert!(!untouched(&tasks[0], &[series]));
+    }
+
+    #[test]
+    fn ends() {
+        assert!(parse("daily until 9/1", start(), start()).is_err());
+        assert!(parse("daily until 9/3", start(), start()).unwrap().end == End::Until(start()));
+        assert!(parse("daily for 3 times", start(), start()).unwrap().end == End::Count(3));
+        assert!(parse("every 2 days 1 time", start(), start()).unwrap().end == End::Count(1));
+        assert!(parse("daily 0 times", start(), start()).is_err());
+        assert_eq!(parse("daily until 9/5", start(), start()).unwrap().dates(start(), date(2025, 12, 31)).len(), 3);
+    }
+
+    #[test]
+    fn rules() {
+        assert!(parse("every other day", start(), start()).unwrap().rule == Rule::EveryDays(2));
+        assert!(parse("every 1 day", start(), start()).unwrap().rule == Rule::Daily);
+        assert!(parse("weekly", start(), start()).unwrap().rule == Rule::Weekly(vec![Weekday::Wed]));
+        assert!(parse("weekdays", start(), start()).unwrap().dates(start(), date(2025, 9, 9)).len() == 5);
+        assert!(parse("monthly on 32", start(), start()).is_err());
+        assert!(parse("sometimes", start(), start()).is_err());
+        assert!(parse("", start(), start()).is_err());
+    }
+}
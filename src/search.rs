@@ -0,0 +1,155 @@
+use chrono::NaiveDate;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::{Task, entry::TextField, subjects::Subject};
+
+// how well `query` matches `text` as a subsequence, or None if some letter of it is missing.
+// runs of consecutive letters and letters at the start of a word count for more, and gaps
+// between letters cost a little, so "apush" finds "APUSH" before "a pretty unusual shirt"
+fn score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..text.len()).find(|&i| text[i] == c)?;
+        score += 1;
+        if found > 0 && previous == Some(found - 1) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - next).min(10) as i64;
+        previous = Some(found);
+        next = found + 1;
+    }
+    return Some(score);
+}
+
+// indices into `tasks` that match, best first. ties go to unfinished tasks and then to whichever
+// is due closest to today, which is also the whole order when the query is empty
+pub fn rank(query: &str, tasks: &[Task], subjects: &[Subject], today: NaiveDate) -> Vec<usize> {
+    let mut results: Vec<(i64, usize)> = Vec::new();
+    for (i, task) in tasks.iter().enumerate() {
+        let subject = subjects.iter().find(|subject| subject.id == task.subject).map(|subject| subject.name.as_str()).unwrap_or("");
+        let best = [
+            score(query, &task.description),
+            score(query, subject),
+            score(query, &format!("{subject} {}", task.description)),
+        ].into_iter().flatten().max();
+        if let Some(best) = best {
+            results.push((best, i));
+        }
+    }
+    results.sort_by_key(|&(score, i)| (-score, tasks[i].completed.is_some(), (tasks[i].date - today).num_days().abs()));
+    return results.into_iter().map(|(_, i)| i).collect();
+}
+
+pub enum Outcome {
+    Open,
+    Close,
+    Choose(usize), // an index into App::tasks
+}
+
+// state for the search overlay, opened over the current tab with /
+pub struct SearchScreen {
+    pub query: TextField,
+    pub row: usize,
+    pub results: Vec<usize>,
+}
+
+impl SearchScreen {
+    pub fn new() -> SearchScreen {
+        return SearchScreen { query: TextField::default(), row: 0, results: Vec::new() };
+    }
+
+    pub fn update(&mut self, tasks: &[Task], subjects: &[Subject], today: NaiveDate) {
+        self.results = rank(&self.query.text, tasks, subjects, today);
+        self.row = self.row.min(self.results.len().saturating_sub(1));
+    }
+
+    // letters go to the query, so moving through the results is on the arrows and ctrl-n/p
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Outcome::Close,
+            KeyCode::Enter => return match self.results.get(self.row) {
+                Some(&index) => Outcome::Choose(index),
+                None => Outcome::Open,
+            },
+            KeyCode::Up => self.row = self.row.saturating_sub(1),
+            KeyCode::Char('p') if control => self.row = self.row.saturating_sub(1),
+            KeyCode::Down => self.row = (self.row + 1).min(self.results.len().saturating_sub(1)),
+            KeyCode::Char('n') if control => self.row = (self.row + 1).min(self.results.len().saturating_sub(1)),
+            _ => if self.query.handle_key(key) {
+                self.row = 0;
+            },
+        }
+        return Outcome::Open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+
+    fn date(day: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(2025, 9, day).unwrap();
+    }
+
+    fn task(subject: u32, description: &str, day: u32, completed: bool) -> Task {
+        return Task {
+            subject,
+            description: description.to_string(),
+            date: date(day),
+            time: None,
+            completed: completed.then(|| date(day).and_hms_opt(12, 0, 0).unwrap()),
+            priority: Priority::Normal,
+            occurrence: None,
+        };
+    }
+
+    fn subjects() -> Vec<Subject> {
+        return vec![Subject::new(1, "APUSH", (255, 0, 0)), Subject::new(2, "Physics", (0, 255, 0))];
+    }
+
+    #[test]
+    fn scoring() {
+        assert_eq!(score("lab", "Physics homework"), None);
+        assert_eq!(score("bal", "lab report"), None);
+        assert!(score("LAB", "lab report").is_some());
+        assert_eq!(score("lab rep", "lab report"), score("labrep", "lab report"));
+        assert_eq!(score("", "anything"), Some(0));
+        // a run beats the same letters spread out, and starting a word beats the middle of one
+        assert!(score("rep", "lab report") > score("rep", "read the paper"));
+        assert!(score("ess", "essay") > score("ess", "chess"));
+        assert!(score("apush", "APUSH") > score("apush", "a pretty unusual shirt"));
+    }
+
+    #[test]
+    fn ranking() {
+        let tasks = vec![
+            task(2, "buy a pretty unusual shirt", 3, false),
+            task(1, "chapter 5 notes", 20, false),
+            task(2, "lab report", 4, false),
+        ];
+        assert_eq!(rank("apush", &tasks, &subjects(), date(3)), [1, 0]);
+        // the subject and description are also matched together
+        assert_eq!(rank("phys lab", &tasks, &subjects(), date(3)), [2]);
+        assert_eq!(rank("quiz", &tasks, &subjects(), date(3)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn ties() {
+        let tasks = vec![
+            task(1, "reading", 10, false),
+            task(1, "reading", 4, true),
+            task(1, "reading", 1, false),
+            task(1, "reading", 6, false),
+        ];
+        // unfinished before finished, then closest to today on either side
+        assert_eq!(rank("reading", &tasks, &subjects(), date(5)), [3, 2, 0, 1]);
+        assert_eq!(rank("", &tasks, &subjects(), date(5)), [3, 2, 0, 1]);
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::{Datelike, Days, Months, NaiveDate};
+use crossterm::event::{KeyCode, KeyEvent};
+use crate::App;
+
+fn shift(date: NaiveDate, days: i64) -> NaiveDate {
+    let shifted = if days < 0 {
+        date.checked_sub_days(Days::new(days.unsigned_abs()))
+    } else {
+        date.checked_add_days(Days::new(days as u64))
+    };
+    return shifted.unwrap_or(date);
+}
+
+impl App {
+
+    // visible tasks due on a date, in the order the week and month tabs draw them
+    pub fn tasks_on(&self, date: NaiveDate) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.tasks.len())
+            .filter(|&i| self.tasks[i].date == date && self.visible(&self.tasks[i]))
+            .collect();
+        self.sort_for_view(&mut indices);
+        return indices;
+    }
+
+    // the task every action (complete, edit, delete) applies to, if anything is selected
+    pub fn selected_task(&self) -> Option<usize> {
+        return match self.tab {
+            0 => self.agenda_rows().get(self.agenda_row).copied(),
+            1 | 2 => self.tasks_on(self.cursor).get(self.cursor_task).copied(),
+            _ => None,
+        };
+    }
+
+    // the first and last day the current tab is showing
+    pub fn view_range(&self) -> (NaiveDate, NaiveDate) {
+        return match self.tab {
+            1 => {
+                let week = self.view.week(self.config.week_start);
+                (week.first_day(), week.last_day())
+            },
+            _ => {
+                let first = self.view.with_day(1).unwrap_or(self.view);
+                (first, shift(first, first.num_days_in_month() as i64 - 1))
+            },
+        };
+    }
+
+    // keeps the cursor inside whatever the week or month tab is showing
+    pub fn clamp_cursor(&self, date: NaiveDate) -> NaiveDate {
+        let (first, last) = self.view_range();
+        return date.clamp(first, last);
+    }
+
+    // walking the cursor off the edge of the view brings the neighbouring week or month along
+    fn move_cursor(&mut self, days: i64) {
+        let moved = shift(self.cursor, days);
+        let (first, last) = self.view_range();
+        if moved < first || moved > last {
+            self.view = moved;
+        }
+        if moved != self.cursor {
+            self.cursor = moved;
+            self.cursor_task = 0;
+        }
+    }
+
+    fn page(&mut self, forward: bool) {
+        let (view, cursor) = match (self.tab, forward) {
+            (1, true) => (shift(self.view, 7), shift(self.cursor, 7)),
+            (1, false) => (shift(self.view, -7), shift(self.cursor, -7)),
+            (_, true) => (
+                self.view.checked_add_months(Months::new(1)).unwrap_or(self.view),
+                self.cursor.checked_add_months(Months::new(1)).unwrap_or(self.cursor),
+            ),
+            (_, false) => (
+                self.view.checked_sub_months(Months::new(1)).unwrap_or(self.view),
+                self.cursor.checked_sub_months(Months::new(1)).unwrap_or(self.cursor),
+            ),
+        };
+        self.view = view;
+        self.cursor = cursor;
+        self.cursor_task = 0;
+    }
+
+    pub fn jump_to_today(&mut self) {
+        self.view = self.today;
+        self.cursor = self.today;
+        self.cursor_task = 0;
+        self.agenda_row = 0;
+    }
+
+    // where a search result takes you: the today tab keeps it if it's listed there, otherwise the
+    // week tab (or the month tab, if that's where you are) opens on its day. a task the subject
+    // filter hides clears the filter
+    pub fn jump_to_task(&mut self, index: usize) {
+        if !self.visible(&self.tasks[index]) {
+            self.show_completed = true;
+        }
+        if !self.visible(&self.tasks[index]) {
+            for subject in &mut self.subjects {
+                subject.filtered = false;
+            }
+        }
+        if self.tab == 3 || (self.tab == 0 && !self.agenda_rows().contains(&index)) {
+            self.tab = 1;
+        }
+        self.select_task(index);
+    }
+
+    // moves the selection onto a task, bringing its week or month into view if need be
+    pub fn select_task(&mut self, index: usize) {
+        match self.tab {
+            0 => if let Some(row) = self.agenda_rows().iter().position(|&i| i == index) {
+                self.agenda_row = row;
+            },
+            1 | 2 => {
+                let date = self.tasks[index].date;
+                let (first, last) = self.view_range();
+                if date < first || date > last {
+                    self.view = date;
+                }
+                self.cursor = date;
+                self.cursor_task = self.tasks_on(date).iter().position(|&i| i == index).unwrap_or(0);
+            },
+            _ => {},
+        }
+    }
+
+    fn cycle_cursor_task(&mut self, forward: bool) {
+        let count = self.tasks_on(self.cursor).len();
+        if count == 0 {
+            return;
+        }
+        self.cursor_task = match forward {
+            true => (self.cursor_task + 1) % count,
+            false => (self.cursor_task + count - 1) % count,
+        };
+    }
+
+    // returns false if the key isn't a movement key on the current tab
+    pub fn handle_navigation(&mut self, key: KeyEvent) -> bool {
+        match (self.tab, key.code) {
+            (0, KeyCode::Up | KeyCode::Char('k')) => self.agenda_row = self.agenda_row.saturating_sub(1),
+            (0, KeyCode::Down | KeyCode::Char('j')) => self.agenda_row += 1,
+
+            // the week tab stacks days vertically and lays each day's tasks out in a row
+            (1, KeyCode::Up | KeyCode::Char('k')) => self.move_cursor(-1),
+            (1, KeyCode::Down | KeyCode::Char('j')) => self.move_cursor(1),
+            (1, KeyCode::Left | KeyCode::Char('h')) => self.cursor_task = self.cursor_task.saturating_sub(1),
+            (1, KeyCode::Right | KeyCode::Char('l')) => self.cursor_task += 1,
+
+            (2, KeyCode::Up | KeyCode::Char('k')) => self.move_cursor(-7),
+            (2, KeyCode::Down | KeyCode::Char('j')) => self.move_cursor(7),
+            (2, KeyCode::Left | KeyCode::Char('h')) => self.move_cursor(-1),
+            (2, KeyCode::Right | KeyCode::Char('l')) => self.move_cursor(1),
+
+            (1 | 2, KeyCode::Char('[') | KeyCode::PageUp) => self.page(false),
+            (1 | 2, KeyCode::Char(']') | KeyCode::PageDown) => self.page(true),
+            (0..=2, KeyCode::Char('t')) => self.jump_to_today(),
+
+            (1 | 2, KeyCode::Char('n')) => self.cycle_cursor_task(true),
+            (1 | 2, KeyCode::Char('p')) => self.cycle_cursor_task(false),
+            _ => return false,
+        }
+
+        self.cursor = self.clamp_cursor(self.cursor);
+        self.cursor_task = self.cursor_task.min(self.tasks_on(self.cursor).len().saturating_sub(1));
+        self.render_current_tab();
+        return true;
+    }
+}
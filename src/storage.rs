@@ -0,0 +1,429 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::{
+    env, fs,
+    io::{Error, ErrorKind, Result, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use crate::{Priority, Task, history::{History, Step}, recurrence::{self, Series}};
+
+// the task file is plain text, one record per line, fields separated by tabs:
+//     task	<subject id>	<yyyy-mm-dd>	<description>	<completed at, or empty>	<hh:mm due, or empty>	<series id:yyyy-mm-dd, or empty>	<priority, or empty for normal>
+//     series	<id>	<subject id>	<first yyyy-mm-dd>	<rule, like "weekly on mon,wed">	<description>	<hh:mm due, or empty>	<skipped yyyy-mm-dd,...>	<priority, or empty for normal>
+//     undo	<entry>.<step>	<+ or ->	<position>	<a task or series record like the ones above>
+//     redo	<entry>.<step>	<+ or ->	<position>	<a task or series record like the ones above>
+// the undo and redo lines are the history from history.rs, each a task or series that a step put
+// in (+) or took out (-) of its list
+// tabs, newlines and backslashes inside fields are escaped so a line is always one record
+const HEADER: &str = "# doras-todo tasks v1";
+const TIMESTAMP: &str = "%Y-%m-%dT%H:%M:%S";
+const DUE_TIME: &str = "%H:%M";
+
+// $<variable>/doras-todo, falling back on <fallback> under the home directory as the xdg spec says
+pub fn app_dir(variable: &str, fallback: &str) -> PathBuf {
+    let dir = match env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(fallback),
+            None => PathBuf::from("."),
+        },
+    };
+    return dir.join("doras-todo");
+}
+
+pub fn default_path() -> PathBuf {
+    return app_dir("XDG_DATA_HOME", ".local/share").join("tasks.txt");
+}
+
+pub fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+pub fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    return unescaped;
+}
+
+// an error pointing at a line of one of the app's text files
+pub fn invalid(path: &Path, line: usize, message: &str) -> Error {
+    return Error::new(ErrorKind::InvalidData, format!("{}:{}: {message}", path.display(), line + 1));
+}
+
+// the settings in a "key = value" file like config.txt or a theme, with their line numbers.
+// blank lines and lines starting with # are skipped
+pub fn settings<'a>(contents: &'a str, path: &'a Path) -> impl Iterator<Item = Result<(usize, &'a str, &'a str)>> + 'a {
+    let lines = contents.lines().enumerate().map(|(line, text)| (line, text.trim()));
+    return lines.filter(|(_, text)| !text.is_empty() && !text.starts_with('#')).map(|(line, text)| {
+        let (key, value) = text.split_once('=').ok_or_else(|| invalid(path, line, "expected \"key = value\""))?;
+        return Ok((line, key.trim(), value.trim()));
+    });
+}
+
+fn parse_task(fields: &[&str], path: &Path, line: usize) -> Result<Task> {
+    if fields.len() < 4 {
+        return Err(invalid(path, line, "expected subject, date and description"));
+    }
+    let subject = fields[1].parse::<u32>().map_err(|_| invalid(path, line, &format!("\"{}\" is not a subject id", fields[1])))?;
+    let date = NaiveDate::parse_from_str(fields[2], "%Y-%m-%d")
+        .map_err(|_| invalid(path, line, &format!("\"{}\" is not a valid date", fields[2])))?;
+    // trailing fields were added over time, so older files simply don't have them
+    let completed = match fields.get(4) {
+        Some(field) if !field.is_empty() => Some(NaiveDateTime::parse_from_str(field, TIMESTAMP)
+            .map_err(|_| invalid(path, line, &format!("\"{field}\" is not a valid completion time")))?),
+        _ => None,
+    };
+    let time = match fields.get(5) {
+        Some(field) if !field.is_empty() => Some(NaiveTime::parse_from_str(field, DUE_TIME)
+            .map_err(|_| invalid(path, line, &format!("\"{field}\" is not a valid due time")))?),
+        _ => None,
+    };
+    let occurrence = match fields.get(6) {
+        Some(field) if !field.is_empty() => {
+            let parsed = field.split_once(':').and_then(|(id, date)| Some((
+                id.parse::<u32>().ok()?,
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?,
+            )));
+            Some(parsed.ok_or_else(|| invalid(path, line, &format!("\"{field}\" is not a series id and date")))?)
+        },
+        _ => None,
+    };
+    let priority = parse_priority(fields.get(7), path, line)?;
+    return Ok(Task { subject, description: unescape(fields[3]), date, time, completed, priority, occurrence });
+}
+
+fn parse_priority(field: Option<&&str>, path: &Path, line: usize) -> Result<Priority> {
+    let field = field.unwrap_or(&"");
+    return Priority::parse(field).ok_or_else(|| invalid(path, line, &format!("\"{field}\" is not a priority")));
+}
+
+fn format_priority(priority: Priority) -> &'static str {
+    return if priority == Priority::Normal { "" } else { priority.name() };
+}
+
+fn parse_series(fields: &[&str], path: &Path, line: usize) -> Result<Series> {
+    if fields.len() < 6 {
+        return Err(invalid(path, line, "expected id, subject, start date, rule and description"));
+    }
+    let id = fields[1].parse::<u32>().map_err(|_| invalid(path, line, &format!("\"{}\" is not a valid id", fields[1])))?;
+    let subject = fields[2].parse::<u32>().map_err(|_| invalid(path, line, &format!("\"{}\" is not a subject id", fields[2])))?;
+    let start = NaiveDate::parse_from_str(fields[3], "%Y-%m-%d")
+        .map_err(|_| invalid(path, line, &format!("\"{}\" is not a valid date", fields[3])))?;
+    let recurrence = recurrence::parse(fields[4], start, start).map_err(|error| invalid(path, line, &error))?;
+    let time = match fields.get(6) {
+        Some(field) if !field.is_empty() => Some(NaiveTime::parse_from_str(field, DUE_TIME)
+            .map_err(|_| invalid(path, line, &format!("\"{field}\" is not a valid due time")))?),
+        _ => None,
+    };
+    let mut skipped = Vec::new();
+    for date in fields.get(7).unwrap_or(&"").split(',').filter(|date| !date.is_empty()) {
+        skipped.push(NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| invalid(path, line, &format!("\"{date}\" is not a valid date")))?);
+    }
+    let priority = parse_priority(fields.get(8), path, line)?;
+    return Ok(Series { id, subject, description: unescape(fields[5]), time, priority, start, recurrence, skipped });
+}
+
+// adds one undo or redo line to the entry and step it names, which come in order
+fn parse_history(fields: &[&str], stack: &mut Vec<Vec<Step>>, path: &Path, line: usize) -> Result<()> {
+    if fields.len() < 5 {
+        return Err(invalid(path, line, "expected entry, + or -, position and a record"));
+    }
+    let numbers = fields[1].split_once('.').and_then(|(entry, step)| Some((entry.parse::<usize>().ok()?, step.parse::<usize>().ok()?)));
+    let Some((entry, step)) = numbers.filter(|&(entry, step)| entry <= stack.len() && step <= stack.get(entry).map_or(0, |steps| steps.len())) else {
+        return Err(invalid(path, line, &format!("\"{}\" is not the current or next entry and step", fields[1])));
+    };
+    if entry == stack.len() {
+        stack.push(Vec::new());
+    }
+    if step == stack[entry].len() {
+        stack[entry].push(Step::default());
+    }
+    let step = &mut stack[entry][step];
+    let position = fields[3].parse::<usize>().map_err(|_| invalid(path, line, &format!("\"{}\" is not a position", fields[3])))?;
+    let added = match fields[2] {
+        "+" => true,
+        "-" => false,
+        other => return Err(invalid(path, line, &format!("\"{other}\" is not + or -"))),
+    };
+    let record = &fields[4..];
+    match (record[0], added) {
+        ("task", true) => step.tasks.added.push((position, parse_task(record, path, line)?)),
+        ("task", false) => step.tasks.removed.push((position, parse_task(record, path, line)?)),
+        ("series", true) => step.series.added.push((position, parse_series(record, path, line)?)),
+        ("series", false) => step.series.removed.push((position, parse_series(record, path, line)?)),
+        (other, _) => return Err(invalid(path, line, &format!("unknown record \"{other}\""))),
+    }
+    return Ok(());
+}
+
+pub fn load(path: &Path) -> Result<(Vec<Task>, Vec<Series>, History)> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok((Vec::new(), Vec::new(), History::default())),
+        Err(error) => return Err(error),
+    };
+
+    let mut tasks = Vec::new();
+    let mut series = Vec::new();
+    let mut history = History::default();
+    for (line, text) in contents.lines().enumerate() {
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = text.split('\t').collect();
+        match fields[0] {
+            "task" => tasks.push(parse_task(&fields, path, line)?),
+            "series" => series.push(parse_series(&fields, path, line)?),
+            "undo" => parse_history(&fields, &mut history.undo, path, line)?,
+            "redo" => parse_history(&fields, &mut history.redo, path, line)?,
+            other => return Err(invalid(path, line, &format!("unknown record \"{other}\""))),
+        }
+    }
+    return Ok((tasks, series, history));
+}
+
+// writes everything to a sibling temp file first and renames it over the real one,
+// so a crash halfway through a save never leaves a truncated file behind
+pub fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() && !dir.as_os_str().is_empty() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, path)?;
+    return Ok(());
+}
+
+fn format_series(series: &Series) -> String {
+    let skipped: Vec<String> = series.skipped.iter().map(|date| date.format("%Y-%m-%d").to_string()).collect();
+    return format!(
+        "series\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        series.id,
+        series.subject,
+        series.start.format("%Y-%m-%d"),
+        series.recurrence.format(),
+        escape(&series.description),
+        series.time.map(|time| time.format(DUE_TIME).to_string()).unwrap_or_default(),
+        skipped.join(","),
+        format_priority(series.priority),
+    );
+}
+
+fn format_task(task: &Task) -> String {
+    return format!(
+        "task\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        task.subject,
+        task.date.format("%Y-%m-%d"),
+        escape(&task.description),
+        task.completed.map(|time| time.format(TIMESTAMP).to_string()).unwrap_or_default(),
+        task.time.map(|time| time.format(DUE_TIME).to_string()).unwrap_or_default(),
+        task.occurrence.map(|(id, date)| format!("{id}:{}", date.format("%Y-%m-%d"))).unwrap_or_default(),
+        format_priority(task.priority),
+    );
+}
+
+fn format_history(kind: &str, stack: &[Vec<Step>]) -> String {
+    let mut lines = String::new();
+    for (entry, steps) in stack.iter().enumerate() {
+        for (number, step) in steps.iter().enumerate() {
+            let records = step.tasks.removed.iter().map(|(i, task)| ("-", i, format_task(task)))
+                .chain(step.tasks.added.iter().map(|(i, task)| ("+", i, format_task(task))))
+                .chain(step.series.removed.iter().map(|(i, series)| ("-", i, format_series(series))))
+                .chain(step.series.added.iter().map(|(i, series)| ("+", i, format_series(series))));
+            for (sign, position, record) in records {
+                lines.push_str(&format!("{kind}\t{entry}.{number}\t{sign}\t{position}\t{record}\n"));
+            }
+        }
+    }
+    return lines;
+}
+
+// the whole file as save writes it
+pub fn format(tasks: &[Task], series: &[Series], history: &History) -> String {
+    let mut contents = String::new();
+    contents.push_str(HEADER);
+    contents.push('\n');
+    for series in series {
+        contents.push_str(&format_series(series));
+        contents.push('\n');
+    }
+    let horizon = recurrence::horizon(chrono::Local::now().date_naive());
+    for task in tasks.iter().filter(|task| task.date <= horizon || !recurrence::untouched(task, series)) {
+        contents.push_str(&format_task(task));
+        contents.push('\n');
+    }
+    contents.push_str(&format_history("undo", &history.undo));
+    contents.push_str(&format_history("redo", &history.redo));
+    return contents;
+}
+
+pub fn save(path: &Path, tasks: &[Task], series: &[Series], history: &History) -> Result<()> {
+    return write_atomically(path, &format(tasks, series, history));
+}
+
+// when the file was last written, by this process or any other, or None if there isn't one yet
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    return fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Change;
+
+    fn date(day: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(2025, 9, day).unwrap();
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        return env::temp_dir().join(format!("doras-todo-test-{}-{name}.txt", std::process::id()));
+    }
+
+    // everything a file can hold: a series with a skipped date, its occurrences, a finished task
+    // with a time, awkward characters, and history on both sides
+    fn sample() -> (Vec<Task>, Vec<Series>, History) {
+        let mut series = vec![Series {
+            id: 1,
+            subject: 2,
+            description: "Reading\\notes".to_string(),
+            time: NaiveTime::from_hms_opt(9, 30, 0),
+            priority: Priority::High,
+            start: date(3),
+            recurrence: recurrence::parse("weekly on mon,wed 10 times", date(3), date(3)).unwrap(),
+            skipped: vec![date(8)],
+        }];
+        let mut tasks = vec![Task {
+            subject: 1,
+            description: "Lab report\twith a tab\nand a newline".to_string(),
+            date: date(4),
+            time: NaiveTime::from_hms_opt(15, 0, 0),
+            completed: None,
+            priority: Priority::Urgent,
+            occurrence: None,
+        }];
+        recurrence::expand(&series, &mut tasks, date(12));
+
+        let mut history = History::default();
+        // finished at a whole second, since that's all the file keeps
+        let completed = date(5).and_hms_opt(22, 15, 0);
+        let step = Step::record(Change::Edit(0, Task { completed, ..tasks[0].clone() }), &mut tasks, &mut series);
+        history.push(vec![step]);
+        let first = Step::record(Change::Delete(1), &mut tasks, &mut series);
+        let second = Step::record(Change::Add(Task { description: "Essay".to_string(), occurrence: None, ..tasks[0].clone() }), &mut tasks, &mut series);
+        history.push(vec![first, second]);
+        history.undo(&mut tasks, &mut series);
+        return (tasks, series, history);
+    }
+
+    #[test]
+    fn escaping() {
+        for text in ["plain", "tab\there", "new\nline", "back\\slash", "\\t isn't a tab", "trailing\\", "\r\n"] {
+            assert_eq!(unescape(&escape(text)), text);
+            assert!(!escape(text).contains(['\t', '\n', '\r']));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let (tasks, series, history) = sample();
+        let path = temp_path("round-trip");
+        save(&path, &tasks, &series, &history).unwrap();
+        let (loaded_tasks, loaded_series, loaded_history) = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(loaded_tasks == tasks);
+        assert!(loaded_series == series);
+        assert_eq!(loaded_tasks[0].description, "Lab report\twith a tab\nand a newline");
+        assert_eq!(loaded_series[0].description, "Reading\\notes");
+        assert_eq!((loaded_history.undo.len(), loaded_history.redo.len()), (1, 1));
+        assert_eq!(loaded_history.redo[0].len(), 2);
+        assert_eq!(format(&loaded_tasks, &loaded_series, &loaded_history), format(&tasks, &series, &history));
+    }
+
+    #[test]
+    fn loaded_history_still_undoes() {
+        let (tasks, series, history) = sample();
+        let path = temp_path("undo");
+        save(&path, &tasks, &series, &history).unwrap();
+        let (mut tasks, mut series, mut history) = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // redoing the skip and the add, then undoing both and the finishing before them
+        assert!(history.redo(&mut tasks, &mut series).is_some());
+        assert_eq!(series[0].skipped, [date(8), date(3)]);
+        assert_eq!(tasks.last().unwrap().description, "Essay");
+        assert!(history.undo(&mut tasks, &mut series).is_some());
+        assert!(history.undo(&mut tasks, &mut series).is_some());
+        assert_eq!(series[0].skipped, [date(8)]);
+        assert!(tasks[0].completed.is_none());
+    }
+
+    #[test]
+    fn bad_history_lines() {
+        let path = temp_path("bad-history");
+        let task = "task\t1\t2025-09-03\tHomework\t\t\t\t";
+        for line in [
+            format!("undo\t1.0\t+\t0\t{task}"), // entries have to come in order
+            format!("undo\t0.1\t+\t0\t{task}"), // and so do steps
+            format!("undo\t0.0\t*\t0\t{task}"),
+            format!("undo\t0.0\t+\tfirst\t{task}"),
+            "undo\t0.0\t+\t0\tnote\tsomething".to_string(),
+            "undo\t0.0\t+".to_string(),
+        ] {
+            fs::write(&path, format!("{HEADER}\n{task}\n{line}\n")).unwrap();
+            let error = load(&path).err().expect(&line);
+            assert!(error.to_string().contains(":3: "), "{error}");
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn settings_lines() {
+        let path = Path::new("config.txt");
+        let contents = "# a comment\n\n  week_start = mon  \ntheme=light\n   # indented comment\ncolors = 256 = 16\n";
+        let read: Vec<_> = settings(contents, path).map(Result::unwrap).collect();
+        assert_eq!(read, [(2, "week_start", "mon"), (3, "theme", "light"), (5, "colors", "256 = 16")]);
+        let error = settings("stats = true\nstats\n", path).nth(1).unwrap().unwrap_err();
+        assert_eq!(error.to_string(), "config.txt:2: expected \"key = value\"");
+    }
+
+    #[test]
+    fn subjects_are_ids() {
+        let path = temp_path("subject-names");
+        fs::write(&path, format!("{HEADER}\ntask\t3\t2025-09-03\tHomework\t\t\t\t\ntask\tPhysics\t2025-09-04\tLab\t\t\t\t\n")).unwrap();
+        let error = load(&path).err().expect("a subject given by name");
+        fs::remove_file(&path).unwrap();
+        assert!(error.to_string().ends_with(":3: \"Physics\" is not a subject id"), "{error}");
+    }
+}
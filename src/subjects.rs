@@ -0,0 +1,227 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use std::{
+    fs,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+use crate::{entry::TextField, storage::{self, invalid}};
+
+// the subjects file lives with the rest of the config and is meant to be hand editable too:
+//     <id>	<code>	<name>	<#rrggbb>	[archived]	[filter]
+// tasks point at a subject by id, so renaming one here never orphans anything
+const HEADER: &str = "# doras-todo subjects: id, short code, name, color, \"archived\" to hide it from new tasks, \"filter\" to only show these";
+
+// colors handed out to new subjects, in order
+const PALETTE: [(u8, u8, u8); 8] = [
+    (255, 127, 127),
+    (0, 255, 0),
+    (0, 255, 255),
+    (255, 0, 0),
+    (0, 0, 255),
+    (255, 255, 0),
+    (255, 127, 255),
+    (255, 160, 0),
+];
+
+#[derive(Clone, PartialEq)]
+pub struct Subject {
+    pub id: u32,
+    pub code: String,
+    pub name: String,
+    pub color: (u8, u8, u8),
+    pub archived: bool,
+    pub filtered: bool, // while any subject is filtered, the views only show tasks from those
+}
+
+impl Subject {
+    pub fn new(id: u32, name: &str, color: (u8, u8, u8)) -> Subject {
+        let code: String = name.chars().filter(|c| c.is_alphanumeric()).take(4).collect();
+        return Subject { id, code: code.to_uppercase(), name: name.to_string(), color, archived: false, filtered: false };
+    }
+
+    // stands in for a subject that was deleted from the file by hand
+    pub fn missing(id: u32) -> Subject {
+        return Subject { id, code: "?".to_string(), name: format!("#{id}"), color: (127, 127, 127), archived: true, filtered: false };
+    }
+}
+
+// what a fresh install starts with, the same list that used to be hardcoded
+fn defaults() -> Vec<Subject> {
+    return vec![
+        Subject::new(1, "Film", (255, 127, 127)),
+        Subject::new(2, "Physics", (0, 255, 0)),
+        Subject::new(3, "Stats", (0, 255, 255)),
+        Subject::new(4, "APUSH", (255, 0, 0)),
+        Subject::new(5, "Compsci", (0, 0, 255)),
+        Subject::new(6, "Lang", (255, 255, 0)),
+        Subject::new(7, "None", (127, 127, 127)),
+    ];
+}
+
+pub fn config_dir() -> PathBuf {
+    return storage::app_dir("XDG_CONFIG_HOME", ".config");
+}
+
+pub fn default_path() -> PathBuf {
+    return config_dir().join("subjects.txt");
+}
+
+pub fn parse_color(text: &str) -> Option<(u8, u8, u8)> {
+    let hex = text.trim().strip_prefix('#').unwrap_or(text.trim());
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    return Some((channel(0)?, channel(2)?, channel(4)?));
+}
+
+pub fn format_color(color: (u8, u8, u8)) -> String {
+    return format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2);
+}
+
+pub fn load(path: &Path) -> Result<Vec<Subject>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            let subjects = defaults();
+            save(path, &subjects)?;
+            return Ok(subjects);
+        },
+        Err(error) => return Err(error),
+    };
+
+    let mut subjects: Vec<Subject> = Vec::new();
+    for (line, text) in contents.lines().enumerate() {
+        if text.trim().is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = text.split('\t').collect();
+        if fields.len() < 4 {
+            return Err(invalid(path, line, "expected id, code, name and color"));
+        }
+        let id = fields[0].trim().parse::<u32>()
+            .map_err(|_| invalid(path, line, &format!("\"{}\" is not a valid id", fields[0])))?;
+        if subjects.iter().any(|subject| subject.id == id) {
+            return Err(invalid(path, line, &format!("id {id} is used twice")));
+        }
+        let color = parse_color(fields[3])
+            .ok_or_else(|| invalid(path, line, &format!("\"{}\" is not a color like #ff7f7f", fields[3])))?;
+        subjects.push(Subject {
+            id,
+            code: storage::unescape(fields[1]),
+            name: storage::unescape(fields[2]),
+            color,
+            archived: fields[4..].iter().any(|field| field.trim() == "archived"),
+            filtered: fields[4..].iter().any(|field| field.trim() == "filter"),
+        });
+    }
+    return Ok(subjects);
+}
+
+pub fn save(path: &Path, subjects: &[Subject]) -> Result<()> {
+    let mut contents = String::new();
+    contents.push_str(HEADER);
+    contents.push('\n');
+    for subject in subjects {
+        contents.push_str(&format!(
+            "{}\t{}\t{}\t{}{}{}\n",
+            subject.id,
+            storage::escape(&subject.code),
+            storage::escape(&subject.name),
+            format_color(subject.color),
+            if subject.archived { "\tarchived" } else { "" },
+            if subject.filtered { "\tfilter" } else { "" },
+        ));
+    }
+    return storage::write_atomically(path, &contents);
+}
+
+pub fn next_id(subjects: &[Subject]) -> u32 {
+    return subjects.iter().map(|subject| subject.id).max().unwrap_or(0) + 1;
+}
+
+// finds a subject by id, name or short code, which is what the task file and the cli accept
+pub fn lookup(subjects: &[Subject], text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Ok(id) = text.parse::<u32>() && subjects.iter().any(|subject| subject.id == id) {
+        return Some(id);
+    }
+    return subjects.iter()
+        .find(|subject| subject.name.eq_ignore_ascii_case(text) || subject.code.eq_ignore_ascii_case(text))
+        .map(|subject| subject.id);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Prompt {
+    Add,
+    Rename,
+    Code,
+    Color,
+}
+
+// state for the subject management screen, opened over the current tab
+pub struct SubjectsScreen {
+    pub row: usize,
+    pub prompt: Option<(Prompt, TextField)>,
+    pub error: Option<String>,
+}
+
+impl SubjectsScreen {
+    pub fn new() -> SubjectsScreen {
+        return SubjectsScreen { row: 0, prompt: None, error: None };
+    }
+
+    // edits `subjects` in place, returning false once the screen should close
+    pub fn handle_key(&mut self, key: KeyEvent, subjects: &mut Vec<Subject>) -> bool {
+        if let Some((prompt, field)) = &mut self.prompt {
+            match key.code {
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Enter => {
+                    let prompt = *prompt;
+                    let text = field.text.trim().to_string();
+                    match self.finish_prompt(prompt, &text, subjects) {
+                        Ok(()) => { self.prompt = None; self.error = None; },
+                        Err(error) => self.error = Some(error),
+                    }
+                },
+                _ => { field.handle_key(key); },
+            }
+            return true;
+        }
+
+        self.error = None;
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('s') => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.row = self.row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.row = (self.row + 1).min(subjects.len().saturating_sub(1)),
+            KeyCode::Char('a') => self.prompt = Some((Prompt::Add, TextField::default())),
+            KeyCode::Char('r') if self.row < subjects.len() => self.prompt = Some((Prompt::Rename, TextField::new(&subjects[self.row].name))),
+            KeyCode::Char('o') if self.row < subjects.len() => self.prompt = Some((Prompt::Code, TextField::new(&subjects[self.row].code))),
+            KeyCode::Char('c') if self.row < subjects.len() => self.prompt = Some((Prompt::Color, TextField::new(&format_color(subjects[self.row].color)))),
+            KeyCode::Char('x') if self.row < subjects.len() => subjects[self.row].archived = !subjects[self.row].archived,
+            KeyCode::Char('f') if self.row < subjects.len() => subjects[self.row].filtered = !subjects[self.row].filtered,
+            _ => {}
+        }
+        return true;
+    }
+
+    fn finish_prompt(&mut self, prompt: Prompt, text: &str, subjects: &mut Vec<Subject>) -> std::result::Result<(), String> {
+        if text.is_empty() {
+            return Err("that can't be empty".to_string());
+        }
+        if text.contains('\t') {
+            return Err("tabs aren't allowed here".to_string());
+        }
+        match prompt {
+            Prompt::Add => {
+                let color = PALETTE[subjects.len() % PALETTE.len()];
+                subjects.push(Subject::new(next_id(subjects), text, color));
+                self.row = subjects.len() - 1;
+            },
+            Prompt::Rename => subjects[self.row].name = text.to_string(),
+            Prompt::Code => subjects[self.row].code = text.to_string(),
+            Prompt::Color => subjects[self.row].color = parse_color(text).ok_or(format!("\"{text}\" is not a color like #ff7f7f"))?,
+        }
+        return Ok(());
+    }
+}
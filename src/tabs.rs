@@ -1,25 +1,299 @@
 use crossterm::style::Color;
-use chrono::{Datelike, Days, Weekday};
-use crate::{App, Task, Subject};
-
-fn task_color(task: &Task) -> Color {
-    let color: (u8, u8, u8) = match task.subject {
-        Subject::Film => (255, 127, 127),
-        Subject::Physics => (0, 255, 0),
-        Subject::Stats => (0, 255, 255),
-        Subject::APUSH => (255, 0, 0),
-        Subject::Compsci => (0, 0, 255),
-        Subject::Lang => (255, 255, 0),
-        Subject::None => (127, 127, 127),
+use std::{cmp::Reverse, ops::Range};
+use chrono::{Datelike, Days, NaiveDate};
+use crate::{App, Priority, Task, config::SortOrder, dates, recurrence, entry::{EntryField, TextField}, subjects::Prompt, text::{self, truncate}, theme::Theme};
+
+// the two cell marker drawn in front of every task
+fn swatch(task: &Task) -> &'static str {
+    return if task.completed.is_some() { "✓ " } else { "██" };
+}
+
+// drawn in the cell right after the swatch, normal priority gets nothing
+fn priority_marker(priority: Priority, theme: &Theme) -> Option<(char, Color)> {
+    return match priority {
+        Priority::Low => Some(('↓', theme.dim)),
+        Priority::Normal => None,
+        Priority::High => Some(('↑', theme.warning)),
+        Priority::Urgent => Some(('!', theme.alert)),
     };
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AgendaSection {
+    Overdue,
+    Today,
+    Upcoming,
+}
 
-    return Color::Rgb{ r: color.0, g: color.1, b: color.2 }; 
+// one line of the today tab
+#[derive(Clone, Copy)]
+enum AgendaLine {
+    Header(AgendaSection, usize), // with how many tasks the section has
+    Empty(AgendaSection),
+    Task(usize), // an index into App::tasks
+    Gap,
+}
+
+fn shift_days(date: NaiveDate, days: u64) -> NaiveDate {
+    return date.checked_add_days(Days::new(days)).unwrap_or(date);
+}
+
+// what the week tab shows for a task, its due time first when it has one
+fn label(task: &Task) -> String {
+    return match task.time {
+        Some(time) => format!("{} {}", dates::format_time(time), task.description),
+        None => task.description.clone(),
+    };
 }
 
 impl App {
 
+    pub fn subject_color(&self, id: u32) -> Color {
+        return self.config.theme.subject_color(self.subject(id).color);
+    }
+
+    fn task_color(&self, task: &Task) -> Color {
+        return self.subject_color(task.subject);
+    }
+
+    fn render_swatch(&mut self, task: &Task, x: usize, y: usize) {
+        self.render_string(swatch(task), x, y);
+        self.color_area(self.task_color(task), x, y, x + 1, y);
+    }
+
+    fn render_priority(&mut self, task: &Task, x: usize, y: usize) {
+        if let Some((marker, color)) = priority_marker(task.priority, &self.config.theme) {
+            self.screen_text[y][x] = marker;
+            self.color_area(color, x, y, x, y);
+        }
+    }
+
+    // puts tasks in the order the current tab is set to show them in
+    pub fn sort_for_view(&self, indices: &mut [usize]) {
+        let order = self.config.sort[self.tab.min(2)];
+        let subject_rank = |id: u32| self.subjects.iter().position(|subject| subject.id == id).unwrap_or(usize::MAX);
+        match order {
+            SortOrder::Date => indices.sort_by_key(|&i| self.tasks[i].due()),
+            SortOrder::Priority => indices.sort_by_key(|&i| (Reverse(self.tasks[i].priority), self.tasks[i].due())),
+            SortOrder::Subject => indices.sort_by_key(|&i| (subject_rank(self.tasks[i].subject), self.tasks[i].due())),
+        }
+    }
+
+    // positions into `indices`, most important first and otherwise in the order they're drawn.
+    // when a row or cell runs out of room, the ones at the end of this list give way first
+    fn by_importance(&self, indices: &[usize]) -> Vec<usize> {
+        let mut positions: Vec<usize> = (0..indices.len()).collect();
+        positions.sort_by_key(|&position| Reverse(self.tasks[indices[position]].priority));
+        return positions;
+    }
+
+    // fills in series occurrences for everything on screen, and the today tab's week ahead either way.
+    // the month grid runs up to two weeks past the end of its month
+    pub fn expand_series(&mut self) {
+        let mut last = recurrence::horizon(self.today);
+        match self.tab {
+            1 => last = last.max(self.view_range().1),
+            2 => last = last.max(shift_days(self.view_range().1, 13)),
+            _ => {},
+        }
+        recurrence::expand(&self.series, &mut self.tasks, last);
+    }
+
+    // indices into self.tasks for each section of the today tab, in the tab's sort order
+    pub fn agenda(&self) -> Vec<(AgendaSection, Vec<usize>)> {
+        let horizon = recurrence::horizon(self.today);
+        let mut sections = vec![
+            (AgendaSection::Overdue, Vec::new()),
+            (AgendaSection::Today, Vec::new()),
+            (AgendaSection::Upcoming, Vec::new()),
+        ];
+        for (i, task) in self.tasks.iter().enumerate() {
+            if !self.visible(task) {
+                continue;
+            }
+            // finished work isn't overdue anymore, it just drops off
+            if task.overdue(self.now) {
+                sections[0].1.push(i);
+            } else if task.date == self.today {
+                sections[1].1.push(i);
+            } else if task.date < self.today {
+                continue;
+            } else if task.date <= horizon {
+                sections[2].1.push(i);
+            }
+        }
+        for (_, indices) in &mut sections {
+            self.sort_for_view(indices);
+        }
+        return sections;
+    }
+
+    // the today tab's rows in the order they're drawn, which is what agenda_row counts through
+    pub fn agenda_rows(&self) -> Vec<usize> {
+        return self.agenda().into_iter().flat_map(|(_, indices)| indices).collect();
+    }
+
+    fn render_agenda_row(&mut self, index: usize, y: usize, selected: bool) {
+        let task = self.tasks[index].clone();
+        let right = self.width - 4;
+
+        let day = match (task.date - self.today).num_days() {
+            0 => String::new(),
+            1 => "tomorrow".to_string(),
+            -1 => "yesterday".to_string(),
+            days if days < 0 => format!("{} days ago", -days),
+            _ => task.date.format("%a %b %-d").to_string(),
+        };
+        let when = match task.time {
+            Some(time) if day.is_empty() => dates::format_time(time),
+            Some(time) => format!("{day} {}", dates::format_time(time)),
+            None => day,
+        };
+        let when_x = right.saturating_sub(text::width(&when));
+
+        self.render_swatch(&task, 4, y);
+        self.render_priority(&task, 6, y);
+        self.render_string(&truncate(&self.subject(task.subject).name, 8), 7, y);
+        self.color_area(self.task_color(&task), 7, y, 14, y);
+        let description = truncate(&task.description, when_x.saturating_sub(18));
+        self.render_string(&description, 16, y);
+        if task.completed.is_some() {
+            self.dim_area(7, y, right - 1, y);
+        } else if task.overdue(self.now) {
+            self.color_area(self.config.theme.alert, 16, y, right - 1, y);
+        }
+        if selected {
+            self.color_area(self.config.theme.selected, 16, y, 15 + text::width(&description), y);
+        }
+        if !when.is_empty() && when_x > 16 {
+            self.render_string(&when, when_x, y);
+            self.dim_area(when_x, y, right - 1, y);
+        }
+    }
+
+    // the today tab top to bottom, before any of it is cut to fit the screen
+    fn agenda_lines(&self) -> Vec<AgendaLine> {
+        let mut lines = Vec::new();
+        for (section, indices) in self.agenda() {
+            if section == AgendaSection::Overdue && indices.is_empty() {
+                continue;
+            }
+            lines.push(AgendaLine::Header(section, indices.len()));
+            if indices.is_empty() {
+                lines.push(AgendaLine::Empty(section));
+            }
+            lines.extend(indices.into_iter().map(AgendaLine::Task));
+            lines.push(AgendaLine::Gap);
+        }
+        lines.pop();
+        return lines;
+    }
+
     pub fn render_today_tab(&mut self) {
+        let top = 5;
+        let room = self.height - 2 - top + 1;
+        self.agenda_row = self.agenda_row.min(self.agenda_rows().len().saturating_sub(1));
+        let lines = self.agenda_lines();
+        let tasks_in = |lines: &[AgendaLine]| lines.iter().filter(|line| matches!(line, AgendaLine::Task(_))).count();
 
+        // when it doesn't all fit, scroll so the selected row stays on screen with a line under it
+        // for the "more" marker, and the first line shown gives way to a marker for what's above.
+        // with no tasks left after it, the list scrolls to its end instead
+        let mut first = 0;
+        if lines.len() > room && let Some(selected) = (0..lines.len()).filter(|&i| matches!(lines[i], AgendaLine::Task(_))).nth(self.agenda_row) {
+            first = match tasks_in(&lines[selected + 1..]) {
+                0 => lines.len() - room,
+                _ => (selected + 2).saturating_sub(room).min(lines.len() - room),
+            };
+            first = first.min(selected.saturating_sub(1));
+        }
+        let last = (first + room).min(lines.len());
+
+        let mut row = tasks_in(&lines[..first]);
+        for (y, line) in (top..).zip(&lines[first..last]) {
+            if y == top && first > 0 {
+                let earlier = format!("… {} earlier", tasks_in(&lines[..=first]));
+                self.render_string(&earlier, 4, y);
+                self.dim_area(4, y, 3 + text::width(&earlier), y);
+                if matches!(line, AgendaLine::Task(_)) {
+                    row += 1;
+                }
+                continue;
+            }
+            if y == top + room - 1 && last < lines.len() {
+                let more = format!("… {} more", tasks_in(&lines[y - top + first..]));
+                self.render_string(&more, 4, y);
+                self.dim_area(4, y, 3 + text::width(&more), y);
+                break;
+            }
+            match *line {
+                AgendaLine::Header(section, count) => {
+                    let header = match section {
+                        AgendaSection::Overdue => format!("Overdue ({count})"),
+                        AgendaSection::Today => format!("Today, {}", self.today.format("%A %B %-d")),
+                        AgendaSection::Upcoming => format!("Coming up in the next {} days", recurrence::HORIZON_DAYS),
+                    };
+                    let header = truncate(&header, self.width - 8);
+                    self.render_string(&header, 3, y);
+                    match section {
+                        AgendaSection::Overdue => self.color_area(self.config.theme.alert, 3, y, 2 + text::width(&header), y),
+                        AgendaSection::Today => self.color_area(self.config.theme.today, 3, y, 2 + text::width(&header), y),
+                        AgendaSection::Upcoming => {},
+                    }
+                },
+                AgendaLine::Empty(section) => {
+                    let empty = if section == AgendaSection::Today { "nothing due today" } else { "nothing coming up" };
+                    self.render_string(empty, 4, y);
+                    self.dim_area(4, y, 3 + empty.len(), y);
+                },
+                AgendaLine::Task(index) => {
+                    self.render_agenda_row(index, y, row == self.agenda_row);
+                    if row == self.agenda_row {
+                        self.render_string("›", 2, y);
+                        self.color_area(self.config.theme.selected, 2, y, 2, y);
+                    }
+                    row += 1;
+                },
+                AgendaLine::Gap => {},
+            }
+        }
+    }
+
+    // names the sort order and the week or month on display, right aligned in the border under the tabs
+    pub fn render_view_header(&mut self) {
+        for i in 51..(self.width - 2) {
+            self.screen_text[3][i] = '─';
+        }
+        let order = self.config.sort[self.tab.min(2)].name();
+        // the week or month on display matters more than the sort, so when it all doesn't fit
+        // the sort goes first and then the dates get shorter
+        let dates = match self.tab {
+            1 => {
+                let week = self.view.week(self.config.week_start);
+                let (first, last) = (week.first_day(), week.last_day());
+                vec![
+                    format!("{} – {}", first.format("%b %-d"), last.format("%b %-d, %Y")),
+                    format!("{} – {}", first.format("%b %-d"), last.format("%b %-d")),
+                    format!("{} – {}", first.format("%b %-d"), last.format(if first.month() == last.month() { "%-d" } else { "%b %-d" })),
+                ]
+            },
+            2 => vec![self.view.format("%B %Y").to_string(), self.view.format("%b %Y").to_string()],
+            0 => Vec::new(),
+            _ => return,
+        };
+        let mut headers = vec![format!(" by {order} ")];
+        if !dates.is_empty() {
+            headers = vec![format!(" by {order} · {} ", dates[0])];
+            headers.extend(dates.iter().map(|dates| format!(" {dates} ")));
+        }
+        for header in headers {
+            let x = (self.width - 3).saturating_sub(text::width(&header));
+            if x > 51 {
+                self.render_string(&header, x, 3);
+                self.color_area(self.config.theme.text, x, 3, self.width - 4, 3);
+                return;
+            }
+        }
     }
 
     pub fn render_week_tab(&mut self) {
@@ -33,90 +307,157 @@ impl App {
             self.screen_text[row][self.width - 3] = '┤';
         }
 
-        self.render_string(" Sunday ",    1, 4);
-        self.render_string(" Monday ",    1, vertical_spacing * 1 + 4);
-        self.render_string(" Tuesday ",   1, vertical_spacing * 2 + 4);
-        self.render_string(" Wednesday ", 1, vertical_spacing * 3 + 4);
-        self.render_string(" Thursday ",  1, vertical_spacing * 4 + 4);
-        self.render_string(" Friday ",    1, vertical_spacing * 5 + 4);
-        self.render_string(" Saturday ",  1, vertical_spacing * 6 + 4);
-
-        let (weekday, width) = match self.today.weekday(){
-            Weekday::Sun => (0, 8),
-            Weekday::Mon => (1, 8),
-            Weekday::Tue => (2, 8),
-            Weekday::Wed => (3, 11),
-            Weekday::Thu => (4, 10),
-            Weekday::Fri => (5, 8),
-            Weekday::Sat => (6, 10),
-        };
-        let y = weekday * vertical_spacing + 4;
-        self.color_area(Color::Rgb{r: 255, g: 200, b: 50 }, 1 + width, y, self.width - 3, y);
-
-        let mut tasks_by_weekday: [Vec<Task>; 7] = [Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()];
-        for task in &self.tasks {
-            if task.date.week(Weekday::Sun) == self.today.week(Weekday::Sun) {
-                let task_weekday = match task.date.weekday(){
-                    Weekday::Sun => 0,
-                    Weekday::Mon => 1,
-                    Weekday::Tue => 2,
-                    Weekday::Wed => 3,
-                    Weekday::Thu => 4,
-                    Weekday::Fri => 5,
-                    Weekday::Sat => 6,
-                };
-                tasks_by_weekday[task_weekday].push(task.clone());
+        let week = self.view.week(self.config.week_start);
+        let mut label_widths = [0; 7];
+        for i in 0..7 {
+            let label = shift_days(week.first_day(), i as u64).format(" %A ").to_string();
+            label_widths[i] = text::width(&label);
+            self.render_string(&label, 1, vertical_spacing * i + 4);
+        }
+
+        if week.days().contains(&self.today) {
+            let weekday = self.today.weekday().days_since(self.config.week_start) as usize;
+            let y = weekday * vertical_spacing + 4;
+            self.color_area(self.config.theme.today, 1 + label_widths[weekday], y, self.width - 3, y);
+        }
+
+        let selected = self.selected_task();
+        let cursor_weekday = self.cursor.weekday().days_since(self.config.week_start) as usize;
+        let y = cursor_weekday * vertical_spacing + 4;
+        self.color_area(self.config.theme.selected, 2, y, label_widths[cursor_weekday] - 1, y);
+
+        let mut tasks_by_weekday: [Vec<usize>; 7] = [Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+        for (i, task) in self.tasks.iter().enumerate() {
+            if week.days().contains(&task.date) && self.visible(task) {
+                let task_weekday = task.date.weekday().days_since(self.config.week_start) as usize;
+                tasks_by_weekday[task_weekday].push(i);
             }
         }
+        for indices in &mut tasks_by_weekday {
+            self.sort_for_view(indices);
+        }
 
         for weekday in 0..7 {
             let y = weekday * vertical_spacing + 4 + (vertical_spacing / 2);
-            let tasks = &tasks_by_weekday[weekday];
+            let indices = tasks_by_weekday[weekday].clone();
+            let tasks: Vec<Task> = indices.iter().map(|&i| self.tasks[i].clone()).collect();
+            let labels: Vec<String> = tasks.iter().map(label).collect();
             if tasks.len() == 1 {
-                self.render_string("██ ", 2, y);
-                self.render_string(&tasks[0].description, 5, y);
-                self.color_area(task_color(&tasks[0]), 2, y, 3, y);  
-            } else if tasks.len() > 1 {
-                let mut width = 0;
-                for i in 0..tasks.len() {
-                    width += 6;
-                    width += tasks[i].description.len();
+                self.render_swatch(&tasks[0], 2, y);
+                self.render_priority(&tasks[0], 4, y);
+                let shown = self.render_clipped(&labels[0], 5, y, self.width - 3);
+                if tasks[0].completed.is_some() {
+                    self.dim_area(5, y, 4 + shown, y);
                 }
-                width -= 3;
-                let mut minimized = 0;
-                while width > (self.width - 3) {
-                    width = 0;
+                if selected == Some(indices[0]) {
+                    self.color_area(self.config.theme.selected, 5, y, 4 + shown, y);
+                }
+            } else if tasks.len() > 1 {
+                // when the row is too narrow the least important tasks shrink down to their swatch first
+                let ranked = self.by_importance(&indices);
+                let mut expanded = vec![true; tasks.len()];
+                let row_width = |expanded: &[bool]| -> usize {
+                    let mut width = 0;
                     for i in 0..tasks.len() {
                         width += 5;
-                        if i < (tasks.len() - minimized) {
-                            width += tasks[i].description.len() + 1;
+                        if expanded[i] {
+                            width += text::width(&labels[i]) + 1;
                         }
                     }
-                    minimized += 1;
+                    return width - 3;
+                };
+                for &position in ranked.iter().rev() {
+                    if row_width(&expanded) <= self.width - 3 {
+                        break;
+                    }
+                    expanded[position] = false;
                 }
-                if minimized > 0 { minimized -= 1; }
                 let mut x = 2;
                 for i in 0..tasks.len() {
+                    // even down to swatches a long enough list runs out of room
+                    if x + 3 > self.width - 3 {
+                        break;
+                    }
                     self.screen_text[y][x - 2] = '│';
-                    self.render_string("██ ", x, y);
-                    self.color_area(task_color(&tasks[i]), x, y, x + 1, y);
+                    self.render_swatch(&tasks[i], x, y);
+                    self.render_priority(&tasks[i], x + 2, y);
+                    if selected == Some(indices[i]) {
+                        // a minimized task has no description to light up, so mark its divider instead
+                        self.color_area(self.config.theme.selected, x - 2, y, x - 2, y);
+                    }
                     x += 5;
                     
-                    if i < (tasks.len() - minimized) {
-                        self.render_string(&tasks[i].description, x - 2, y);
-                        x += tasks[i].description.len() + 1;
+                    if expanded[i] {
+                        let shown = self.render_clipped(&labels[i], x - 2, y, self.width - 3);
+                        if tasks[i].completed.is_some() {
+                            self.dim_area(x - 2, y, x - 3 + shown, y);
+                        }
+                        if selected == Some(indices[i]) {
+                            self.color_area(self.config.theme.selected, x - 2, y, x - 3 + shown, y);
+                        }
+                        x += shown + 1;
                     }
                 }
             }
         }
     }
 
-    pub fn render_month_tab(&mut self) {
-        let mut min_week = self.today.week(Weekday::Sun);
-        while min_week.last_day().month() == self.today.month() {
-            min_week = min_week.first_day().checked_sub_days(Days::new(7)).expect("date error?").week(Weekday::Sun); // this is just min_week--;
+    // x and y are the top left corner of the cell's border, lines is how many rows fit inside it
+    fn render_month_cell(&mut self, tasks: &[usize], x: usize, y: usize, width: usize, lines: usize, dimmed: bool) {
+        if lines == 0 {
+            // no room under the date, so squeeze a count in next to it
+            let count = format!("+{}", tasks.len());
+            if width > 4 + count.len() {
+                self.render_string(&count, x + width - count.len(), y);
+                if dimmed {
+                    self.dim_area(x + width - count.len(), y, x + width - 1, y);
+                }
+            }
+            return;
         }
-        min_week = min_week.first_day().checked_add_days(Days::new(7)).expect("date error??").week(Weekday::Sun); // min_week++;
+
+        let shown = if tasks.len() > lines { lines - 1 } else { tasks.len() };
+        // the most important tasks get the lines, except the selected task is never hidden
+        // behind "+N more". whichever are shown stay in the tab's sort order
+        let selected = self.selected_task();
+        let mut kept: Vec<usize> = self.by_importance(tasks).into_iter().take(shown).collect();
+        if let Some(position) = tasks.iter().position(|&index| Some(index) == selected) && !kept.contains(&position) && shown > 0 {
+            kept[shown - 1] = position;
+        }
+        kept.sort();
+        for (line, &position) in kept.iter().enumerate() {
+            let index = tasks[position];
+            let task = self.tasks[index].clone();
+            let row = y + 1 + line;
+            self.render_swatch(&task, x, row);
+            if width > 2 {
+                self.render_priority(&task, x + 2, row);
+            }
+            if width > 3 {
+                let description = truncate(&task.description, width - 3);
+                self.render_string(&description, x + 3, row);
+                if task.completed.is_some() {
+                    self.dim_area(x + 3, row, x + 2 + text::width(&description), row);
+                }
+                if Some(index) == selected {
+                    self.color_area(self.config.theme.selected, x + 3, row, x + 2 + text::width(&description), row);
+                }
+            }
+        }
+        if shown < tasks.len() {
+            let more = truncate(&format!("+{} more", tasks.len() - shown), width);
+            self.render_string(&more, x, y + lines);
+            self.dim_area(x, y + lines, x + text::width(&more) - 1, y + lines);
+        }
+        if dimmed {
+            self.dim_area(x, y + 1, x + width - 1, y + lines);
+        }
+    }
+
+    pub fn render_month_tab(&mut self) {
+        let week_start = self.config.week_start;
+        // the grid starts with the week holding the 1st, wherever in the month the view is
+        let min_week = self.view.with_day(1).expect("every month has a 1st").week(week_start);
 
         let mut first_day = min_week.first_day();
         let mut day_offset = 0;
@@ -126,7 +467,7 @@ impl App {
         }
         let last_day = day_offset + min_week.last_day().num_days_in_month() as usize;
         let reduced = last_day <= 35;
-        let max_week = min_week.first_day().checked_add_days(Days::new(if reduced {28} else {35})).expect("date error???").week(Weekday::Sun); // max_week = min_week + 4 or 5
+        let max_week = min_week.first_day().checked_add_days(Days::new(if reduced {28} else {35})).expect("date error???").week(week_start); // max_week = min_week + 4 or 5
 
         let horizontal_spacing = (self.width - 4) / 7;
         let rows = if reduced {5} else {6};
@@ -171,17 +512,34 @@ impl App {
             self.screen_text[row][right] = '┤';
         }
 
+        let mut tasks_by_cell: Vec<Vec<usize>> = vec![Vec::new(); rows * 7];
         for i in 0..self.tasks.len() {
             let task = &self.tasks[i];
-            if task.date >= min_week.first_day() && task.date <= max_week.last_day() {
-                let mut row = 0;
-                while task.date >= min_week.last_day().checked_add_days(Days::new(7 * row)).expect("date error????") {
-                    row += 1;
-                }
+            if task.date >= min_week.first_day() && task.date <= max_week.last_day() && self.visible(task) {
+                let cell = (task.date - min_week.first_day()).num_days() as usize;
+                tasks_by_cell[cell].push(i);
             }
         }
+        for indices in &mut tasks_by_cell {
+            self.sort_for_view(indices);
+        }
 
-        for i in 0..self.today.num_days_in_month() as usize {
+        for (cell, tasks) in tasks_by_cell.iter().enumerate() {
+            if tasks.is_empty() {
+                continue;
+            }
+            let x = 2 + horizontal_spacing * (cell % 7);
+            let y = 4 + vertical_spacing * (cell / 7);
+            let dimmed = cell < day_offset || cell >= last_day;
+            self.render_month_cell(tasks, x, y, horizontal_spacing - 1, vertical_spacing - 1, dimmed);
+        }
+
+        let cursor_cell = (self.cursor - min_week.first_day()).num_days() as usize;
+        let x = 2 + horizontal_spacing * (cursor_cell % 7);
+        let y = 4 + vertical_spacing * (cursor_cell / 7);
+        self.color_area(self.config.theme.selected, x, y, x + horizontal_spacing - 2, y);
+
+        for i in 0..self.view.num_days_in_month() as usize {
             self.render_string(
                 format!("{}", i + 1).as_str(), 
                 3 + horizontal_spacing * ((i + day_offset) % 7), 
@@ -264,7 +622,339 @@ impl App {
         }
     }
 
+    fn render_text_field(&mut self, field: &TextField, focused: bool, x: usize, y: usize, width: usize) {
+        let (text, cursor) = field.visible(width);
+        self.render_string(&text, x, y);
+        if focused {
+            if cursor >= text::width(&text) {
+                self.screen_text[y][x + cursor] = '█';
+            }
+            self.color_area(self.config.theme.selected, x + cursor, y, x + cursor, y);
+        }
+    }
+
+    // a bordered box in the middle of the tab area, drawn over whatever is already there.
+    // returns where the first line of text starts so callers can color parts of it
+    pub fn render_dialog(&mut self, lines: &[String]) -> (usize, usize) {
+        let inner = lines.iter().map(|line| text::width(line)).max().unwrap_or(0).min(self.width - 10);
+        let left = (self.width - inner) / 2 - 2;
+        let right = left + inner + 3;
+        let top = (4 + self.height) / 2 - (lines.len() + 2) / 2;
+        let bottom = top + lines.len() + 1;
+
+        for y in top..=bottom {
+            for x in left..=right {
+                self.screen_text[y][x] = ' ';
+                self.screen_color[y][x] = self.config.theme.text;
+            }
+        }
+        for x in left + 1..right {
+            self.screen_text[top][x] = '─';
+            self.screen_text[bottom][x] = '─';
+        }
+        for y in top + 1..bottom {
+            self.screen_text[y][left] = '│';
+            self.screen_text[y][right] = '│';
+        }
+        self.screen_text[top][left] = '╭';
+        self.screen_text[top][right] = '╮';
+        self.screen_text[bottom][left] = '╰';
+        self.screen_text[bottom][right] = '╯';
+
+        for (i, line) in lines.iter().enumerate() {
+            self.render_string(&truncate(line, inner), left + 2, top + 1 + i);
+        }
+        return (left + 2, top + 1);
+    }
+
+    // a dialog with a title, then a list scrolled so the selected row stays in view, then the
+    // footer lines. each row is its text and whether it's dimmed, and gets the › marker and a swatch
+    // in front. returns where the title is, the inner width and which rows are on screen
+    fn render_list_dialog(&mut self, title: &str, rows: &[(String, bool)], row: usize, empty: &str, footer: &[String]) -> (usize, usize, usize, Range<usize>) {
+        let room = (self.height - 4).saturating_sub(9 + footer.len()).max(1);
+        let first = (row + 1).saturating_sub(room);
+        let shown = first..(first + room).min(rows.len());
+
+        // padding the title keeps the box from jumping around as the list changes
+        let mut lines = vec![format!("{title:<64}"), String::new()];
+        for i in shown.clone() {
+            lines.push(format!("{} ██ {}", if i == row { "›" } else { " " }, rows[i].0));
+        }
+        if shown.is_empty() {
+            lines.push(format!("  {empty}"));
+        }
+        lines.push(String::new());
+        lines.extend(footer.iter().cloned());
+
+        let (x, y) = self.render_dialog(&lines);
+        let inner = text::width(&lines[0]).min(self.width - 10);
+        for (line, i) in shown.clone().enumerate() {
+            let y = y + 2 + line;
+            let end = (x + text::width(&lines[2 + line])).min(x + inner) - 1;
+            if rows[i].1 {
+                self.dim_area(x + 5, y, end, y);
+            }
+            if i == row {
+                self.color_area(self.config.theme.selected, x, y, x, y);
+                self.color_area(self.config.theme.selected, x + 5, y, end, y);
+            }
+        }
+        if shown.is_empty() {
+            self.dim_area(x, y + 2, x + inner - 1, y + 2);
+        }
+        return (x, y, inner, shown);
+    }
+
+    pub fn render_subjects_screen(&mut self) {
+        let Some(screen) = &self.subjects_screen else { return };
+        let row = screen.row;
+        let prompt = screen.prompt.clone();
+        let error = screen.error.clone().unwrap_or_default();
+
+        let rows: Vec<(String, bool)> = self.subjects.iter().map(|subject| (format!(
+            "{} {}{}{}",
+            text::pad(&truncate(&subject.code, 6), 6),
+            subject.name,
+            if subject.archived { "  (archived)" } else { "" },
+            if subject.filtered { "  (filter)" } else { "" },
+        ), subject.archived)).collect();
+        let label = match prompt.as_ref().map(|(kind, _)| kind) {
+            Some(Prompt::Add) => "new subject: ",
+            Some(Prompt::Rename) => "rename to: ",
+            Some(Prompt::Code) => "short code: ",
+            Some(Prompt::Color) => "color (#rrggbb): ",
+            None => "",
+        };
+        let hint = match prompt {
+            Some(_) => "enter save   esc cancel",
+            None => "a add   r rename   o short code   c color   x archive   f filter   esc close",
+        };
+
+        let footer = [label.to_string(), error.clone(), hint.to_string()];
+        let (x, y, inner, shown) = self.render_list_dialog("Subjects", &rows, row, "no subjects yet", &footer);
+        for (line, i) in shown.clone().enumerate() {
+            self.color_area(self.subject_color(self.subjects[i].id), x + 2, y + 2 + line, x + 3, y + 2 + line);
+        }
+
+        let y = y + 3 + shown.len().max(1);
+        if let Some((_, field)) = prompt {
+            let label = text::width(label);
+            self.render_text_field(&field, true, x + label, y, inner - label);
+        }
+        if !error.is_empty() {
+            let end = (x + text::width(&error)).min(x + inner) - 1;
+            self.color_area(self.config.theme.alert, x, y + 1, end, y + 1);
+        }
+        self.dim_area(x, y + 2, x + inner - 1, y + 2);
+    }
+
+    pub fn render_search_screen(&mut self) {
+        let Some(screen) = &self.search else { return };
+        let row = screen.row;
+        let query = screen.query.clone();
+        let results = screen.results.clone();
+
+        let rows: Vec<(String, bool)> = results.iter().map(|&index| {
+            let task = &self.tasks[index];
+            return (format!(
+                "{}  {}  {}{}",
+                self.subject(task.subject).name,
+                task.description,
+                task.date.format("%a %b %-d"),
+                if task.completed.is_some() { " (done)" } else { "" },
+            ), task.completed.is_some());
+        }).collect();
+        let footer = [format!("{} found   ↑/↓ choose   enter jump   esc close", results.len())];
+
+        let (x, y, inner, shown) = self.render_list_dialog("Search: ", &rows, row, "nothing matches", &footer);
+        self.render_text_field(&query, true, x + 8, y, inner - 8);
+        for (line, i) in shown.clone().enumerate() {
+            let task = self.tasks[results[i]].clone();
+            self.render_swatch(&task, x + 2, y + 2 + line);
+        }
+        let hint = y + 3 + shown.len().max(1);
+        self.dim_area(x, hint, x + inner - 1, hint);
+    }
+
     pub fn render_entry_tab(&mut self) {
-        
+        if let Some(index) = self.entry.editing {
+            let heading = truncate(&format!("Editing \"{}\", esc to cancel", self.tasks[index].description), self.width - 8);
+            self.render_string(&heading, 4, 4);
+            self.color_area(self.config.theme.selected, 4, 4, 3 + text::width(&heading), 4);
+        }
+
+        let labels = [
+            (EntryField::Subject, "Subject", 6),
+            (EntryField::Description, "Description", 8),
+            (EntryField::Date, "Date", 10),
+            (EntryField::Time, "Time", 12),
+            (EntryField::Repeat, "Repeat", 14),
+            (EntryField::Priority, "Priority", 16),
+        ];
+        for (field, label, y) in labels {
+            self.render_string(label, 4, y);
+            if field != self.entry.field {
+                self.dim_area(4, y, 4 + label.len(), y);
+            } else {
+                self.render_string("›", 2, y);
+            }
+        }
+
+        let subject = self.subject(self.entry.subject);
+        let name = truncate(&subject.name, self.width - 30);
+        self.render_string(&format!("◀ ██ {name} ▶"), 18, 6);
+        self.color_area(self.subject_color(subject.id), 20, 6, 21, 6);
+        if self.entry.field != EntryField::Subject {
+            self.dim_area(18, 6, 18, 6);
+            self.dim_area(24 + text::width(&name), 6, 24 + text::width(&name), 6);
+        }
+
+        let priority = self.entry.priority;
+        let marker = priority_marker(priority, &self.config.theme).map(|(marker, _)| marker).unwrap_or(' ');
+        self.render_string(&format!("◀ {marker} {} ▶", priority.name()), 18, 16);
+        if let Some((_, color)) = priority_marker(priority, &self.config.theme) {
+            self.color_area(color, 20, 16, 20, 16);
+        }
+        if self.entry.field != EntryField::Priority {
+            self.dim_area(18, 16, 18, 16);
+            self.dim_area(23 + priority.name().len(), 16, 23 + priority.name().len(), 16);
+        }
+
+        let description = self.entry.description.clone();
+        self.render_text_field(&description, self.entry.field == EntryField::Description, 18, 8, self.width - 22);
+        let date = self.entry.date.clone();
+        self.render_text_field(&date, self.entry.field == EntryField::Date, 18, 10, self.width - 22);
+        let time = self.entry.time.clone();
+        self.render_text_field(&time, self.entry.field == EntryField::Time, 18, 12, self.width - 22);
+        let repeat = self.entry.repeat.clone();
+        match self.entry.series.clone() {
+            // an occurrence is edited on its own, the series it came from stays as it is
+            Some(rule) => {
+                let rule = truncate(&format!("{rule}, changes only apply to this one"), self.width - 22);
+                self.render_string(&rule, 18, 14);
+                self.dim_area(18, 14, 17 + text::width(&rule), 14);
+            },
+            None => self.render_text_field(&repeat, self.entry.field == EntryField::Repeat, 18, 14, self.width - 22),
+        }
+
+        // the line under each of the date, time and repeat fields reads back what was understood,
+        // or what went wrong
+        let date = dates::parse_date(&date.text, self.today);
+        let date_preview = match date {
+            Ok(date) => date.format("%A, %B %-d %Y").to_string(),
+            Err(_) => String::new(),
+        };
+        let time_preview = match time.text.trim() {
+            "" => "optional, leave empty for any time that day".to_string(),
+            text => dates::parse_time(text).map(|time| format!("due at {}", dates::format_time(time))).unwrap_or_default(),
+        };
+        let repeat_preview = match (repeat.text.trim(), date) {
+            _ if self.entry.series.is_some() => String::new(),
+            ("", _) => "optional, like \"weekly on mon wed\" or \"every 2 days until 12/19\"".to_string(),
+            (text, Ok(date)) => match recurrence::parse(text, date, self.today) {
+                Ok(recurrence) => {
+                    let next: Vec<String> = recurrence::upcoming(&recurrence, date, 4).iter().map(|date| date.format("%a %b %-d").to_string()).collect();
+                    format!("{}: {}", recurrence.format(), next.join(", "))
+                },
+                Err(_) => String::new(),
+            },
+            (_, Err(_)) => String::new(),
+        };
+        let error_y = match self.entry.field {
+            EntryField::Time => 13,
+            EntryField::Repeat => 15,
+            _ => 11,
+        };
+        for (preview, y) in [(date_preview, 11), (time_preview, 13), (repeat_preview, 15)] {
+            match self.entry.error.clone() {
+                Some(error) if y == error_y => {
+                    let error = truncate(&error, self.width - 22);
+                    self.render_string(&error, 18, y);
+                    self.color_area(self.config.theme.alert, 18, y, 17 + text::width(&error), y);
+                },
+                _ => {
+                    let preview = truncate(&preview, self.width - 22);
+                    self.render_string(&preview, 18, y);
+                    self.dim_area(18, y, 17 + text::width(&preview), y);
+                },
+            }
+        }
+
+        if let Some(notice) = self.entry.notice.clone() {
+            self.render_string(text::fit(&notice, self.width - 8), 4, 18);
+        }
+
+        if self.height > 21 {
+            let hint = match self.entry.editing {
+                Some(_) => "↑/↓ field   ←/→ choose or move cursor   enter save   esc cancel",
+                None => "↑/↓ field   ←/→ choose or move cursor   enter add   esc back",
+            };
+            let hint = text::fit(hint, self.width - 8);
+            self.render_string(hint, 4, self.height - 3);
+            self.dim_area(4, self.height - 3, 3 + text::width(hint), self.height - 3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Months, Weekday};
+    use std::{io::stdout, path::PathBuf};
+    use crate::{config::Config, history::History};
+
+    fn task(description: &str, date: NaiveDate) -> Task {
+        return Task { subject: 1, description: description.to_string(), date, time: None, completed: None, priority: Priority::Normal, occurrence: None };
+    }
+
+    fn app(tasks: Vec<Task>, (width, height): (usize, usize)) -> App {
+        let path = PathBuf::from("/nonexistent/tasks.txt");
+        return App::new(stdout(), (tasks, Vec::new(), History::default()), path.clone(), Vec::new(), path, Config::default(), (width, height));
+    }
+
+    fn screen(app: &App) -> String {
+        return app.screen_text.iter().map(|row| row.iter().collect::<String>()).collect::<Vec<_>>().join("\n");
+    }
+
+    // the view lands on the last day of a month after paging back, or Left from the 1st, and that
+    // day's week can already reach into the next month
+    #[test]
+    fn month_views_ending_a_month() {
+        for week_start in [Weekday::Sun, Weekday::Mon] {
+            let mut first = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+            while first.year() < 2028 {
+                let last = first.checked_add_months(Months::new(1)).unwrap().pred_opt().unwrap();
+                let mut app = app(vec![task("First", first), task("Last", last)], (80, 24));
+                app.config.week_start = week_start;
+                app.tab = 2;
+                app.view = last;
+                app.cursor = last;
+                app.render_current_tab();
+                let screen = screen(&app);
+                assert!(screen.contains("First") && screen.contains("Last"), "{last} from {week_start}:\n{screen}");
+                first = first.checked_add_months(Months::new(1)).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn the_selected_agenda_row_stays_on_screen() {
+        let today = chrono::Local::now().date_naive();
+        let tasks = (0..40).map(|i| task(&format!("Task {i:02}"), if i < 30 { today } else { shift_days(today, 1) })).collect();
+        let mut app = app(tasks, (80, 24));
+        for row in [0, 15, 29, 39] {
+            app.agenda_row = row;
+            app.render_current_tab();
+            let screen = screen(&app);
+            let selected = screen.lines().find(|line| line.contains('›')).unwrap_or_else(|| panic!("row {row}:\n{screen}"));
+            assert!(selected.contains(&format!("Task {row:02}")), "row {row}:\n{screen}");
+        }
+        // the top of the list has nothing above it, and the end nothing after
+        app.agenda_row = 0;
+        app.render_current_tab();
+        assert!(!screen(&app).contains("earlier") && screen(&app).contains("… "));
+        app.agenda_row = 39;
+        app.render_current_tab();
+        assert!(screen(&app).contains("earlier") && !screen(&app).contains("more"));
     }
 }
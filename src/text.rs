@@ -0,0 +1,109 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// how many terminal cells text takes up. it goes by grapheme, so a letter with combining accents
+// is one cell and an emoji joined out of several is two. unicode-width knows which characters are
+// wide and how emoji sequences combine, this only has to deal with graphemes as screen cells
+
+// the screen keeps one character per cell, so the right half of a two cell grapheme is filled
+// with this to say it's taken. nothing else ever puts a nul on screen
+pub const WIDE_TAIL: char = '\0';
+
+// control characters don't draw anything, where unicode-width counts them as one cell, and a
+// grapheme never takes more than the two cells a screen cell and its WIDE_TAIL can hold
+pub fn grapheme_width(grapheme: &str) -> usize {
+    if grapheme.chars().all(char::is_control) {
+        return 0;
+    }
+    return grapheme.width().min(2);
+}
+
+// the width of a screen cell holding `base` with `marks` (the rest of its grapheme) after it
+pub fn cell_width(base: char, marks: &str) -> usize {
+    if marks.is_empty() {
+        return base.width().unwrap_or(0);
+    }
+    return grapheme_width(&format!("{base}{marks}"));
+}
+
+pub fn width(text: &str) -> usize {
+    return text.graphemes(true).map(grapheme_width).sum();
+}
+
+// the longest start of text that fits in `width` cells without splitting a grapheme
+pub fn fit(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        used += grapheme_width(grapheme);
+        if used > width {
+            return &text[..i];
+        }
+    }
+    return text;
+}
+
+// cuts text down to at most `width` cells, ending it with … when anything was cut. when a wide
+// grapheme doesn't fit the result can come up a cell short
+pub fn truncate(text: &str, width: usize) -> String {
+    if self::width(text) <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    return format!("{}…", fit(text, width - 1));
+}
+
+// text followed by enough spaces to take up `width` cells, since format!'s padding counts chars
+pub fn pad(text: &str, width: usize) -> String {
+    return format!("{text}{}", " ".repeat(width.saturating_sub(self::width(text))));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        assert_eq!(width("Homework 2"), 10);
+        assert_eq!(width("café"), 4);
+        assert_eq!(width("cafe\u{301}"), 4);
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(width("한국어"), 6);
+        assert_eq!(width("ｆｕｌｌ"), 8);
+        assert_eq!(width("🎉 party"), 8);
+        assert_eq!(width("👩‍💻"), 2);
+        assert_eq!(width("👍🏽"), 2);
+        assert_eq!(width("🇯🇵"), 2);
+        assert_eq!(width("❤️"), 2);
+        assert_eq!(width("✓ ██ …"), 6);
+        assert_eq!(width("a\u{200b}b"), 2);
+        assert_eq!(width("a\u{fe0f}"), 1);
+        assert_eq!(width("line\r\nbreak\t"), 9);
+        assert_eq!(width("👨‍👩‍👧 🏳️‍🌈"), 5);
+        assert_eq!(cell_width('日', ""), 2);
+        assert_eq!(cell_width('❤', "\u{fe0f}"), 2);
+        assert_eq!(cell_width('\n', ""), 0);
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(truncate("Homework", 8), "Homework");
+        assert_eq!(truncate("Homework", 5), "Home…");
+        assert_eq!(truncate("Homework", 1), "…");
+        assert_eq!(truncate("Homework", 0), "");
+        assert_eq!(truncate("日本語の宿題", 7), "日本語…");
+        // the fourth character would need cells 7 and 8, leaving no room for the …
+        assert_eq!(truncate("日本語の宿題", 8), "日本語…");
+        assert_eq!(truncate("cafe\u{301} au lait", 5), "cafe\u{301}…");
+        assert_eq!(truncate("👩‍💻👩‍💻👩‍💻", 5), "👩‍💻👩‍💻…");
+    }
+
+    #[test]
+    fn fitting() {
+        assert_eq!(fit("Homework", 4), "Home");
+        assert_eq!(fit("日本語", 5), "日本");
+        assert_eq!(fit("cafe\u{301}", 4), "cafe\u{301}");
+        assert_eq!(fit("🇯🇵🇫🇷", 3), "🇯🇵");
+    }
+}
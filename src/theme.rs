@@ -0,0 +1,289 @@
+use crossterm::style::Color;
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+};
+use crate::{palette::{self, ANSI}, storage::{self, invalid}, subjects};
+
+// a theme is every color the app draws with plus the set of box-drawing glyphs. there are a few
+// built in, and more can be added as themes/<name>.txt next to config.txt, written as "key = value"
+// lines like the config. a file starts from the built-in dark theme, or whichever one `base` names
+//     base = light
+//     text = #202020
+//     background = #f0e8f0 #faf0fa
+//     borders = square
+const BUILT_IN: [&str; 3] = ["dark", "light", "16-color"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Borders {
+    Rounded,
+    Square,
+    Heavy,
+    Ascii,
+}
+
+impl Borders {
+    fn parse(text: &str) -> Option<Borders> {
+        return match text.trim().to_lowercase().as_str() {
+            "rounded" => Some(Borders::Rounded),
+            "square" => Some(Borders::Square),
+            "heavy" => Some(Borders::Heavy),
+            "ascii" => Some(Borders::Ascii),
+            _ => None,
+        };
+    }
+
+    // everything is drawn with the rounded set and swapped over right before it goes to the terminal
+    pub fn glyph(self, c: char) -> char {
+        return match (self, c) {
+            (Borders::Rounded, _) => c,
+            (Borders::Square, '╭') => '┌',
+            (Borders::Square, '╮') => '┐',
+            (Borders::Square, '╰') => '└',
+            (Borders::Square, '╯') => '┘',
+            (Borders::Heavy, _) => match c {
+                '─' => '━',
+                '│' => '┃',
+                '╭' | '┌' => '┏',
+                '╮' | '┐' => '┓',
+                '╰' | '└' => '┗',
+                '╯' | '┘' => '┛',
+                '├' => '┣',
+                '┤' => '┫',
+                '┬' => '┳',
+                '┴' => '┻',
+                '┼' => '╋',
+                _ => c,
+            },
+            (Borders::Ascii, _) => match c {
+                '─' => '-',
+                '│' => '|',
+                '╭' | '╮' | '╰' | '╯' | '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' => '+',
+                _ => c,
+            },
+            _ => c,
+        };
+    }
+}
+
+// how the theme's background colors are laid out, picked in the config rather than the theme
+#[derive(Clone, Copy, PartialEq)]
+pub enum Background {
+    Off,      // the terminal's own
+    Solid,    // halfway between the two colors
+    Gradient, // from the first color in the top left to the second in the bottom right
+    Wave { speed: f64, amplitude: f64 }, // both relative to the default of 1, where the wave spans both colors
+}
+
+impl Background {
+    pub fn parse(text: &str) -> Option<Background> {
+        return match text.trim().to_lowercase().as_str() {
+            "off" | "none" => Some(Background::Off),
+            "solid" => Some(Background::Solid),
+            "gradient" => Some(Background::Gradient),
+            "wave" => Some(Background::Wave { speed: 1., amplitude: 1. }),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SubjectColors {
+    AsIs,
+    Darker, // for light backgrounds, where the bright defaults wash out
+    Ansi,   // the nearest of the 16 standard colors
+}
+
+#[derive(Clone)]
+pub struct Theme {
+    pub name: String,
+    pub text: Color,
+    pub dim: Color,
+    pub today: Color,
+    pub selected: Color, // whatever the cursor is on
+    pub warning: Color,  // high priority
+    pub alert: Color,    // overdue, urgent and errors
+    pub background: Option<[(u8, u8, u8); 2]>, // the wave swings between these, none leaves the terminal's own
+    pub subjects: SubjectColors,
+    pub borders: Borders,
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        return Theme {
+            name: "dark".to_string(),
+            text: Color::White,
+            dim: Color::DarkGrey,
+            today: Color::Rgb{r: 255, g: 200, b: 50 },
+            selected: Color::Rgb{r: 255, g: 200, b: 50 },
+            warning: Color::Rgb{r: 255, g: 160, b: 0 },
+            alert: Color::Rgb{r: 255, g: 80, b: 80 },
+            background: Some([(22, 0, 22), (38, 0, 38)]),
+            subjects: SubjectColors::AsIs,
+            borders: Borders::Rounded,
+        };
+    }
+
+    fn light() -> Theme {
+        return Theme {
+            name: "light".to_string(),
+            text: Color::Rgb{r: 30, g: 30, b: 30 },
+            dim: Color::Rgb{r: 150, g: 150, b: 150 },
+            today: Color::Rgb{r: 190, g: 100, b: 0 },
+            selected: Color::Rgb{r: 190, g: 100, b: 0 },
+            warning: Color::Rgb{r: 200, g: 110, b: 0 },
+            alert: Color::Rgb{r: 200, g: 30, b: 30 },
+            background: Some([(240, 228, 240), (252, 244, 252)]),
+            subjects: SubjectColors::Darker,
+            borders: Borders::Rounded,
+        };
+    }
+
+    // for terminals without truecolor, and for anyone who'd rather their own palette
+    fn ansi() -> Theme {
+        return Theme {
+            name: "16-color".to_string(),
+            text: Color::White,
+            dim: Color::DarkGrey,
+            today: Color::Yellow,
+            selected: Color::Yellow,
+            warning: Color::DarkYellow,
+            alert: Color::Red,
+            background: None,
+            subjects: SubjectColors::Ansi,
+            borders: Borders::Square,
+        };
+    }
+
+    fn built_in(name: &str) -> Option<Theme> {
+        return match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "16-color" | "ansi" => Some(Theme::ansi()),
+            _ => None,
+        };
+    }
+
+    pub fn subject_color(&self, (r, g, b): (u8, u8, u8)) -> Color {
+        return match self.subjects {
+            SubjectColors::AsIs => Color::Rgb{ r, g, b },
+            SubjectColors::Darker => Color::Rgb{ r: (r as f64 * 0.6) as u8, g: (g as f64 * 0.6) as u8, b: (b as f64 * 0.6) as u8 },
+            SubjectColors::Ansi => palette::nearest_ansi((r, g, b)),
+        };
+    }
+
+    // whether the background changes over time, when it doesn't nothing needs redrawing between keys
+    pub fn animated(&self, background: Background) -> bool {
+        return self.background.is_some() && matches!(background, Background::Wave { speed, amplitude } if speed != 0. && amplitude != 0.);
+    }
+
+    // the background of the cell at `x`, `y` on a `width` by `height` screen, `time` seconds in
+    pub fn background_color(&self, background: Background, (x, y): (usize, usize), (width, height): (usize, usize), time: f64) -> Color {
+        let Some([low, high]) = self.background else { return Color::Reset };
+        // how far from the first color to the second
+        let position = match background {
+            Background::Off => return Color::Reset,
+            Background::Solid => 0.5,
+            Background::Gradient => (x as f64 / width.max(1) as f64 + y as f64 / height.max(1) as f64) / 2.,
+            Background::Wave { speed, amplitude } => {
+                let wave = f64::sin(x as f64 * 0.1 - y as f64 * 0.15 + time * 3. * speed);
+                (1. + wave * amplitude.clamp(0., 1.)) / 2.
+            },
+        };
+        let mix = |low: u8, high: u8| (low as f64 + (high as f64 - low as f64) * position).round() as u8;
+        return Color::Rgb{ r: mix(low.0, high.0), g: mix(low.1, high.1), b: mix(low.2, high.2) };
+    }
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+    let name = text.trim().to_lowercase().replace(['-', '_', ' '], "").replace("gray", "grey");
+    let named = ANSI.iter().map(|(color, _)| *color).find(|color| format!("{color:?}").to_lowercase() == name);
+    if let Some(color) = named {
+        return Some(color);
+    }
+    let (r, g, b) = subjects::parse_color(text)?;
+    return Some(Color::Rgb{ r, g, b });
+}
+
+fn parse_rgb(text: &str) -> Option<(u8, u8, u8)> {
+    return match parse_color(text)? {
+        Color::Rgb{ r, g, b } => Some((r, g, b)),
+        color => ANSI.iter().find(|(named, _)| *named == color).map(|(_, rgb)| *rgb),
+    };
+}
+
+pub fn dir() -> PathBuf {
+    return subjects::config_dir().join("themes");
+}
+
+// built-in names win over files, and `seen` stops a file that names itself as its base
+fn load_with(name: &str, seen: &mut Vec<String>) -> Result<Theme> {
+    if let Some(theme) = Theme::built_in(name) {
+        return Ok(theme);
+    }
+    let path = dir().join(format!("{name}.txt"));
+    if seen.iter().any(|seen| seen == name) {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{}: theme \"{name}\" is its own base", path.display())));
+    }
+    seen.push(name.to_string());
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("no theme \"{name}\", try {} or add {}", BUILT_IN.join(", "), path.display()),
+        )),
+        Err(error) => return Err(error),
+    };
+
+    let mut theme = Theme::dark();
+    for setting in storage::settings(&contents, &path) {
+        let (line, key, value) = setting?;
+        let color = || parse_color(value).ok_or_else(|| invalid(&path, line, &format!("\"{value}\" is not a color like #ff7f7f or dark-red")));
+        match key {
+            "base" => theme = load_with(value, seen)?,
+            "text" => theme.text = color()?,
+            "dim" => theme.dim = color()?,
+            "today" => theme.today = color()?,
+            "selected" => theme.selected = color()?,
+            "warning" => theme.warning = color()?,
+            "alert" => theme.alert = color()?,
+            "background" => {
+                let rgb = |text: &str| parse_rgb(text).ok_or_else(|| invalid(&path, line, &format!("\"{text}\" is not a color like #ff7f7f or dark-red")));
+                theme.background = match value.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    ["none"] => None,
+                    [solid] => Some([rgb(solid)?, rgb(solid)?]),
+                    [low, high] => Some([rgb(low)?, rgb(high)?]),
+                    _ => return Err(invalid(&path, line, "expected one color, two for the wave to swing between, or none")),
+                };
+            },
+            "subjects" => theme.subjects = match value {
+                "as-is" => SubjectColors::AsIs,
+                "darker" => SubjectColors::Darker,
+                "16-color" | "ansi" => SubjectColors::Ansi,
+                _ => return Err(invalid(&path, line, &format!("\"{value}\" is not as-is, darker or 16-color"))),
+            },
+            "borders" => theme.borders = Borders::parse(value)
+                .ok_or_else(|| invalid(&path, line, &format!("\"{value}\" is not rounded, square, heavy or ascii")))?,
+            other => return Err(invalid(&path, line, &format!("unknown theme setting \"{other}\""))),
+        }
+    }
+    theme.name = name.to_string();
+    return Ok(theme);
+}
+
+pub fn load(name: &str) -> Result<Theme> {
+    return load_with(name.trim(), &mut Vec::new());
+}
+
+// the built-in themes and then any files, in the order T steps through them
+pub fn available() -> Vec<String> {
+    let mut names: Vec<String> = BUILT_IN.iter().map(|name| name.to_string()).collect();
+    let mut files: Vec<String> = fs::read_dir(dir()).into_iter().flatten().flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.strip_suffix(".txt").map(|name| name.to_string()))
+        .filter(|name| !names.contains(name))
+        .collect();
+    files.sort();
+    names.append(&mut files);
+    return names;
+}